@@ -0,0 +1,264 @@
+#![deny(warnings)]
+
+// #[derive(Grammar)] generates the EBNF spec for a type and the code that
+// builds it out of abackus Trees (see abackus::Grammar).
+//
+// - structs are a sequence of their fields
+// - enums get one alternative per variant, named Type_Variant
+// - #[before("(")] / #[after(")")] add literal tokens around a field
+// - #[token("+")] makes a unit variant (or struct) match a literal
+// - String fields are terminals and need a #[pattern("/[0-9]+/")]
+// - Box<T>, Option<T> and Vec<T> map to T, [ T ] and { T }
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as Tokens;
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields,
+    GenericArgument, LitStr, Path, PathArguments, Type,
+};
+
+#[proc_macro_derive(Grammar, attributes(token, before, after, pattern))]
+pub fn derive_grammar(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// EBNF and builder code for a struct or a variant
+struct Sequence {
+    // expressions evaluating to the EBNF of each element
+    symbols: Vec<Tokens>,
+    // types whose rules are needed
+    deps: Vec<Type>,
+    // expression building the value out of `node`
+    build: Tokens,
+}
+
+fn expand(input: &DeriveInput) -> Result<Tokens, Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics,
+            "Grammar can't be derived for generic types"));
+    }
+    let name = &input.ident;
+    let nonterm = name.to_string();
+    let (rules, build) = match &input.data {
+        Data::Struct(data) => {
+            let seq = sequence(&input.attrs, &data.fields, quote!(#name))?;
+            let Sequence{symbols, deps, build} = seq;
+            let rules = quote! {
+                spec.push(format!("{} := {} ;", #nonterm,
+                                  vec![#(#symbols),*].join(" ")));
+                #(<#deps as ::abackus::Grammar>::rules(spec, done)?;)*
+            };
+            (rules, quote!(Ok(#build)))
+        },
+        Data::Enum(data) => {
+            let mut alternatives = Vec::new();
+            let mut rules = Vec::new();
+            let mut builds = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let alt = format!("{}_{}", nonterm, ident);
+                let seq = sequence(&variant.attrs, &variant.fields,
+                                   quote!(#name::#ident))?;
+                let Sequence{symbols, deps, build} = seq;
+                rules.push(quote! {
+                    ::abackus::typed::claim(done, #alt, owner)?;
+                    spec.push(format!("{} := {} ;", #alt,
+                                      vec![#(#symbols),*].join(" ")));
+                    #(<#deps as ::abackus::Grammar>::rules(spec, done)?;)*
+                });
+                builds.push(quote! {
+                    if head == #alt {
+                        return Ok(#build);
+                    }
+                });
+                alternatives.push(alt);
+            }
+            if alternatives.is_empty() {
+                return Err(Error::new_spanned(name, "Enum has no variants"));
+            }
+            let alternatives = alternatives.join(" | ");
+            let rules = quote! {
+                spec.push(format!("{} := {} ;", #nonterm, #alternatives));
+                #(#rules)*
+            };
+            let build = quote! {
+                let (head, node) = ::abackus::typed::variant(node)?;
+                #(#builds)*
+                Err(format!("Unknown variant {} of {}", head, #nonterm))
+            };
+            (rules, build)
+        },
+        Data::Union(_) => return Err(Error::new_spanned(name,
+            "Grammar can't be derived for unions")),
+    };
+    Ok(quote! {
+        #[automatically_derived]
+        impl ::abackus::Grammar for #name {
+            fn nonterm() -> String {
+                #nonterm.to_string()
+            }
+
+            fn rules(spec: &mut Vec<String>,
+                     done: &mut ::std::collections::HashMap<String, &'static str>)
+                    -> Result<(), String> {
+                // same-named types from other modules can't share the name
+                let owner = ::std::any::type_name::<Self>();
+                if !::abackus::typed::claim(done, &Self::nonterm(), owner)? {
+                    return Ok(());
+                }
+                #rules
+                Ok(())
+            }
+
+            #[allow(unused_variables, clippy::needless_question_mark)]
+            fn from_tree(tree: &::abackus::Tree) -> Result<Self, String> {
+                let node = ::abackus::typed::node(tree, #nonterm)?;
+                #build
+            }
+        }
+    })
+}
+
+// Values of #[name("...")] attributes
+fn literals(attrs: &[Attribute], name: &str) -> Result<Vec<String>, Error> {
+    attrs.iter()
+        .filter(|a| a.path().is_ident(name))
+        .map(|a| a.parse_args::<LitStr>().map(|l| l.value()))
+        .collect()
+}
+
+// EBNF for a literal token
+fn quoted(attrs: &[Attribute], name: &str) -> Result<Vec<Tokens>, Error> {
+    let mut symbols = Vec::new();
+    for (attr, lit) in attrs.iter().filter(|a| a.path().is_ident(name))
+                            .zip(literals(attrs, name)?) {
+        let quoted = match (lit.contains('\''), lit.contains('"')) {
+            (_, false) => format!("\"{}\"", lit),
+            (false, true) => format!("'{}'", lit),
+            (true, true) => return Err(Error::new_spanned(attr,
+                "Tokens can't have both kinds of quotes")),
+        };
+        symbols.push(quote!(#quoted.to_string()));
+    }
+    Ok(symbols)
+}
+
+fn sequence(attrs: &[Attribute], fields: &Fields, ctor: Tokens)
+        -> Result<Sequence, Error> {
+    let mut symbols = quoted(attrs, "token")?;
+    let mut deps = Vec::new();
+    let mut values = Vec::new();
+    for (idx, field) in fields.iter().enumerate() {
+        let label = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => format!("f{}", idx),
+        };
+        let pattern = literals(&field.attrs, "pattern")?.pop();
+        symbols.extend(quoted(&field.attrs, "before")?);
+        let symbol = element(&field.ty, &pattern, &mut deps)?;
+        symbols.push(quote!(format!("{}:{}", #label, #symbol)));
+        symbols.extend(quoted(&field.attrs, "after")?);
+        let value = convert(&field.ty,
+            quote!(::abackus::typed::field(node, #label)?))?;
+        values.push(match &field.ident {
+            Some(ident) => quote!(#ident: #value),
+            None => value,
+        });
+    }
+    if symbols.is_empty() {
+        return Err(Error::new_spanned(&ctor,
+            "Nothing to match, add fields or a #[token(\"...\")]"));
+    }
+    let build = match fields {
+        Fields::Named(_) => quote!(#ctor{#(#values),*}),
+        Fields::Unnamed(_) => quote!(#ctor(#(#values),*)),
+        Fields::Unit => quote!(#ctor),
+    };
+    Ok(Sequence{symbols, deps, build})
+}
+
+// Wrapper types are single-argument generics like Box<T>
+fn wrapped<'t>(ty: &'t Type, wrapper: &str) -> Option<&'t Type> {
+    let path: &Path = match ty {
+        Type::Path(p) if p.qself.is_none() => &p.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != wrapper {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+            match args.args.first() {
+                Some(GenericArgument::Type(inner)) => Some(inner),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+fn is_string(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.qself.is_none() && p.path.is_ident("String"))
+}
+
+// Expression evaluating to the EBNF of a field's type
+fn element(ty: &Type, pattern: &Option<String>, deps: &mut Vec<Type>)
+        -> Result<Tokens, Error> {
+    if let Some(inner) = wrapped(ty, "Box") {
+        return element(inner, pattern, deps);
+    }
+    if let Some(inner) = wrapped(ty, "Option") {
+        let inner = element(inner, pattern, deps)?;
+        return Ok(quote!(format!("[ {} ]", #inner)));
+    }
+    if let Some(inner) = wrapped(ty, "Vec") {
+        let inner = element(inner, pattern, deps)?;
+        return Ok(quote!(format!("{{ {} }}", #inner)));
+    }
+    if is_string(ty) {
+        return match pattern {
+            Some(pattern) => Ok(quote!(#pattern.to_string())),
+            None => Err(Error::new_spanned(ty,
+                "String fields need a #[pattern(\"/regex/\")]")),
+        };
+    }
+    deps.push(ty.clone());
+    Ok(quote!(<#ty as ::abackus::Grammar>::nonterm()))
+}
+
+// Expression building a field's value out of its Tree
+fn convert(ty: &Type, tree: Tokens) -> Result<Tokens, Error> {
+    if let Some(inner) = wrapped(ty, "Box") {
+        let inner = convert(inner, tree)?;
+        return Ok(quote!(Box::new(#inner)));
+    }
+    if let Some(inner) = wrapped(ty, "Option") {
+        let inner = convert(inner, quote!(t))?;
+        return Ok(quote! {
+            match ::abackus::typed::optional(#tree)? {
+                Some(t) => Some(#inner),
+                None => None,
+            }
+        });
+    }
+    if let Some(inner) = wrapped(ty, "Vec") {
+        let inner = convert(inner, quote!(t))?;
+        return Ok(quote! {
+            ::abackus::typed::repeated(#tree)?.into_iter()
+                .map(|t| Ok(#inner))
+                .collect::<Result<Vec<_>, String>>()?
+        });
+    }
+    if is_string(ty) {
+        return Ok(quote!(::abackus::typed::token(#tree)?));
+    }
+    Ok(quote!(<#ty as ::abackus::Grammar>::from_tree(#tree)?))
+}
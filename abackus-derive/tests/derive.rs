@@ -0,0 +1,106 @@
+#![deny(warnings)]
+
+use abackus::ParserBuilder;
+use abackus_derive::Grammar;
+
+#[derive(Debug, PartialEq, Grammar)]
+enum Expr {
+    Add(Box<Expr>, #[before("+")] Term),
+    Term(Term),
+}
+
+#[derive(Debug, PartialEq, Grammar)]
+enum Term {
+    Num(#[pattern("/[0-9]+/")] String),
+    Group(#[before("(")] #[after(")")] Box<Expr>),
+    Neg(Neg),
+}
+
+#[derive(Debug, PartialEq, Grammar)]
+#[token("-")]
+struct Neg {
+    value: Box<Term>,
+}
+
+#[derive(Debug, PartialEq, Grammar)]
+struct Call {
+    #[pattern("[a-z]")]
+    name: String,
+    #[before("(")]
+    #[after(")")]
+    args: Option<Args>,
+}
+
+#[derive(Debug, PartialEq, Grammar)]
+struct Args {
+    first: Expr,
+    rest: Vec<Arg>,
+}
+
+#[derive(Debug, PartialEq, Grammar)]
+struct Arg(#[before(",")] Expr);
+
+fn num(n: &str) -> Term {
+    Term::Num(n.to_string())
+}
+
+#[test]
+fn derived_spec() {
+    assert_eq!(abackus::typed::grammar_spec::<Expr>().unwrap(), [
+        "Expr := Expr_Add | Expr_Term ;",
+        "Expr_Add := f0:Expr \"+\" f1:Term ;",
+        "Term := Term_Num | Term_Group | Term_Neg ;",
+        "Term_Num := f0:/[0-9]+/ ;",
+        "Term_Group := \"(\" f0:Expr \")\" ;",
+        "Term_Neg := f0:Neg ;",
+        "Neg := \"-\" value:Term ;",
+        "Expr_Term := f0:Term ;",
+    ].join("\n"));
+}
+
+#[test]
+fn typed_values() {
+    let parser = ParserBuilder::default().typed_parser::<Expr, _>().unwrap();
+    let values = parser("1 + ( - 2 + 3 )".split_whitespace()).unwrap();
+    assert_eq!(values, vec![
+        Expr::Add(
+            Box::new(Expr::Term(num("1"))),
+            Term::Group(Box::new(Expr::Add(
+                Box::new(Expr::Term(Term::Neg(Neg{value: Box::new(num("2"))}))),
+                num("3"))))),
+    ]);
+    assert!(parser("1 +".split_whitespace()).is_err());
+}
+
+#[test]
+fn optional_and_repeated() {
+    let parser = ParserBuilder::default().typed_parser::<Call, _>().unwrap();
+    let values = parser("f ( )".split_whitespace()).unwrap();
+    assert_eq!(values, vec![Call{name: "f".to_string(), args: None}]);
+    let values = parser("g ( 1 , 2 , 3 )".split_whitespace()).unwrap();
+    assert_eq!(values, vec![Call{name: "g".to_string(), args: Some(Args{
+        first: Expr::Term(num("1")),
+        rest: vec![Arg(Expr::Term(num("2"))), Arg(Expr::Term(num("3")))],
+    })}]);
+}
+
+mod other {
+    #[derive(abackus_derive::Grammar)]
+    #[allow(dead_code)]
+    pub struct Arg(#[pattern("/[a-z]+/")] pub String);
+}
+
+#[derive(Grammar)]
+#[allow(dead_code)]
+struct Clash {
+    arg: Arg,
+    other: other::Arg,
+}
+
+#[test]
+fn same_name_types() {
+    assert_eq!(abackus::typed::grammar_spec::<Clash>(), Err(
+        "Types derive::Arg and derive::other::Arg both use non-terminal Arg".to_string()));
+    let parser = ParserBuilder::default().typed_parser::<Clash, std::str::SplitWhitespace>();
+    assert!(parser.is_err());
+}
@@ -1,21 +1,5 @@
 #![deny(warnings)]
 
-struct Tokenizer<I: Iterator<Item=char>>(lexers::Scanner<I>);
-
-impl<I: Iterator<Item=char>> Iterator for Tokenizer<I> {
-    type Item = String;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.scan_whitespace();
-        self.0.scan_math_op()
-            .or_else(|| self.0.scan_number())
-            .or_else(|| self.0.scan_identifier())
-    }
-}
-
-fn tokenizer<I: Iterator<Item=char>>(input: I) -> Tokenizer<I> {
-    Tokenizer(lexers::Scanner::new(input))
-}
-
 fn main() {
     let grammar = r#"
         expr   := expr ('+'|'-') term | term ;
@@ -29,12 +13,15 @@ fn main() {
     let input = std::env::args().skip(1).
         collect::<Vec<String>>().join(" ");
 
+    // Tokens come from the grammar's literals and plugged terminals. Signs
+    // are left out of num so "2-3" isn't read as "2" "-3".
     use std::str::FromStr;
     let trificator = abackus::ParserBuilder::default()
-        .plug_terminal("num", |n| f64::from_str(n).is_ok())
-        .sexprificator(&grammar, "expr");
+        .plug_terminal("num", |n| !n.starts_with(['-', '+']) && f64::from_str(n).is_ok())
+        .text_sexprificator(grammar, "expr")
+        .unwrap_or_else(|e| panic!("Bad grammar: {}", e));
 
-    match trificator(&mut tokenizer(input.chars())) {
+    match trificator(&input) {
         Ok(trees) => for t in trees { println!("{}", t.print()); },
         Err(e) => println!("{:?}", e)
     }
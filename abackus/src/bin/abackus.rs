@@ -0,0 +1,291 @@
+#![deny(warnings)]
+
+// Grammar workbench: parse input with an EBNF grammar file and print trees.
+//
+//   abackus [options] <grammar-file> <start> [input ...]
+//
+// Input is read from stdin when not given (final newline dropped). Unused
+// and undefined nonterminals are reported on stderr with the parse count.
+// Watching polls the grammar file and the files it @includes.
+
+use abackus::{Dialect, EbnfError, ParserBuilder, Sexpr, Tree, TreeFold};
+use earlgrey::{EarleyParser, Grammar};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const USAGE: &str = "\
+usage: abackus [options] <grammar-file> <start> [input ...]
+  -f, --format ascii|sexpr|json|dot  how to print trees (default ascii)
+  -d, --dialect ebnf|abnf|iso        grammar notation (default ebnf)
+  -t, --tokens grammar|chars|words   how to split input (default grammar,
+                                     chars for abnf)
+  -w, --watch                        re-parse when the grammar file or its
+                                     includes change";
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Format { Ascii, Sexpr, Json, Dot }
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Tokens { Grammar, Chars, Words }
+
+struct Options {
+    format: Format,
+    dialect: Dialect,
+    tokens: Option<Tokens>,
+    watch: bool,
+    grammar: String,
+    start: String,
+    input: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item=String>) -> Result<Options, String> {
+    let mut format = Format::Ascii;
+    let mut dialect = Dialect::Ebnf;
+    let mut tokens = None;
+    let mut watch = false;
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next()
+            .ok_or_else(|| format!("Missing value for {}", name));
+        match arg.as_str() {
+            "-f" | "--format" => format = match value(&arg)?.as_str() {
+                "ascii" => Format::Ascii,
+                "sexpr" => Format::Sexpr,
+                "json" => Format::Json,
+                "dot" => Format::Dot,
+                other => return Err(format!("Unknown format {}", other)),
+            },
+            "-d" | "--dialect" => dialect = match value(&arg)?.as_str() {
+                "ebnf" => Dialect::Ebnf,
+                "abnf" => Dialect::Abnf,
+                "iso" => Dialect::Iso14977,
+                other => return Err(format!("Unknown dialect {}", other)),
+            },
+            "-t" | "--tokens" => tokens = Some(match value(&arg)?.as_str() {
+                "grammar" => Tokens::Grammar,
+                "chars" => Tokens::Chars,
+                "words" => Tokens::Words,
+                other => return Err(format!("Unknown tokens {}", other)),
+            }),
+            "-w" | "--watch" => watch = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ => positional.push(arg),
+        }
+    }
+    if positional.len() < 2 {
+        return Err("Missing grammar file or start symbol".to_string());
+    }
+    let mut positional = positional.into_iter();
+    let grammar = positional.next().unwrap();
+    let start = dialect.nonterm(&positional.next().unwrap());
+    let input: Vec<_> = positional.collect();
+    let input = if input.is_empty() { None } else { Some(input.join(" ")) };
+    Ok(Options{format, dialect, tokens, watch, grammar, start, input})
+}
+
+// Nonterminals unreachable from start, the undefined ones are already
+// reported with their position by the ParserBuilder. Helpers generated for
+// groups and repetitions are left out, the rules using them are reported.
+fn lint(grammar: &Grammar) -> Vec<String> {
+    let heads: HashSet<_> = grammar.rules.iter()
+        .map(|r| r.head.as_str())
+        .filter(|head| !head.starts_with("<Uniq-"))
+        .collect();
+    let mut warnings = Vec::new();
+    let mut reachable = HashSet::new();
+    let mut pending = vec![grammar.start.as_str()];
+    while let Some(head) = pending.pop() {
+        if !reachable.insert(head) {
+            continue;
+        }
+        pending.extend(grammar.rules.iter()
+            .filter(|r| r.head == head)
+            .flat_map(|r| r.spec.iter().filter_map(|s| s.nonterm())));
+    }
+    let mut unused: Vec<_> = heads.difference(&reachable).collect();
+    unused.sort();
+    for nonterm in unused {
+        warnings.push(format!("unused nonterminal {}", nonterm));
+    }
+    warnings
+}
+
+// Same shape sexprificator produces
+struct ToSexpr;
+
+impl TreeFold for ToSexpr {
+    type Output = Sexpr;
+    fn leaf(&mut self, _: &str, lexeme: &str) -> Sexpr {
+        Sexpr::Atom(lexeme.to_string())
+    }
+    fn node(&mut self, _: &str, mut subn: Vec<Sexpr>) -> Sexpr {
+        match subn.len() {
+            1 => subn.swap_remove(0),
+            _ => Sexpr::List(subn),
+        }
+    }
+}
+
+fn print(tree: &Tree, format: Format) {
+    match format {
+        Format::Ascii => print!("{}", tree.fold(&mut ToSexpr).print()),
+        Format::Sexpr => println!("{}", tree),
+        Format::Json => println!("{}", tree.to_json()),
+        Format::Dot => print!("{}", tree.to_dot()),
+    }
+}
+
+// Parse input with the grammar, updating the files the grammar includes
+fn run(options: &Options, input: &str, included: &mut Vec<PathBuf>)
+        -> Result<(), String> {
+    let spec = std::fs::read_to_string(&options.grammar)
+        .map_err(|e| format!("Can't read {}: {}", options.grammar, e))?;
+    let spec = options.dialect.translate(&spec).map_err(|e| e.to_string())?;
+    // later errors point into the translated spec, not the file
+    let spec_error = |e: EbnfError| match options.dialect {
+        Dialect::Ebnf => e.to_string(),
+        _ => format!("{} (position in the grammar translated to the := dialect)", e),
+    };
+    // @include files are also looked up next to the grammar
+    let dir = Path::new(&options.grammar).parent()
+        .unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let parts = ParserBuilder::default().include_dir(dir)
+        .into_parts(&spec, &options.start).map_err(spec_error)?;
+    included.clone_from(&parts.included);
+    for warning in &parts.warnings {
+        eprintln!("warning: {}", spec_error(warning.clone()));
+    }
+    for warning in lint(&parts.grammar) {
+        eprintln!("warning: {}", warning);
+    }
+    let default = match options.dialect {
+        Dialect::Abnf => Tokens::Chars,
+        _ => Tokens::Grammar,
+    };
+    let tokens: Vec<String> = match options.tokens.unwrap_or(default) {
+        Tokens::Grammar => parts.tokenizer.tokenize(input)?,
+        Tokens::Chars => input.chars().map(String::from).collect(),
+        Tokens::Words => input.split_whitespace().map(String::from).collect(),
+    };
+    let tree_builder = parts.tree_builder();
+    let parser = EarleyParser::new(parts.grammar);
+    let trees = tree_builder.eval_all(&parser.parse(tokens.iter())?)?;
+    eprintln!("{} parse tree{}", trees.len(), if trees.len() == 1 { "" } else { "s" });
+    for tree in &trees {
+        print(tree, options.format);
+    }
+    Ok(())
+}
+
+// Modification times of the grammar file and its includes
+fn modified(grammar: &str, included: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    std::iter::once(Path::new(grammar))
+        .chain(included.iter().map(PathBuf::as_path))
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("{}", error);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let input = options.input.clone().unwrap_or_else(|| {
+        let mut buffer = String::new();
+        std::io::stdin().read_to_string(&mut buffer).ok();
+        // like input given as arguments, without the final newline
+        if buffer.ends_with('\n') {
+            buffer.pop();
+        }
+        buffer
+    });
+    let mut included = Vec::new();
+    if !options.watch {
+        if let Err(error) = run(&options, &input, &mut included) {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    // poll the grammar file and its includes and re-parse when they change,
+    // a grammar that fails to parse keeps watching the last known includes
+    let mut last = Vec::new();
+    loop {
+        if modified(&options.grammar, &included) != last {
+            println!("--- {}", options.grammar);
+            if let Err(error) = run(&options, &input, &mut included) {
+                eprintln!("error: {}", error);
+            }
+            last = modified(&options.grammar, &included);
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::{lint, modified, parse_args, run, Format, Tokens};
+    use abackus::{Dialect, ParserBuilder};
+
+    fn args(line: &str) -> Result<super::Options, String> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn arguments() {
+        let options = args("-f json --tokens words g.ebnf expr 1 + 2").unwrap();
+        assert_eq!((options.format, options.tokens), (Format::Json, Some(Tokens::Words)));
+        assert_eq!((options.grammar.as_str(), options.start.as_str()), ("g.ebnf", "expr"));
+        assert_eq!(options.input, Some("1 + 2".to_string()));
+        assert!(!options.watch);
+        let options = args("g.abnf -w --dialect abnf rule-name").unwrap();
+        assert_eq!(options.dialect, Dialect::Abnf);
+        assert_eq!(options.start, Dialect::Abnf.nonterm("rule-name"));
+        assert_eq!((options.format, options.tokens, options.input), (Format::Ascii, None, None));
+        assert!(options.watch);
+        assert_eq!(args("g.ebnf").err(), Some("Missing grammar file or start symbol".to_string()));
+        assert_eq!(args("g.ebnf expr -f").err(), Some("Missing value for -f".to_string()));
+        assert_eq!(args("-f xml g.ebnf expr").err(), Some("Unknown format xml".to_string()));
+        assert_eq!(args("-d bnf g.ebnf expr").err(), Some("Unknown dialect bnf".to_string()));
+        assert_eq!(args("-t bytes g.ebnf expr").err(), Some("Unknown tokens bytes".to_string()));
+        assert_eq!(args("g.ebnf expr --help").err(), Some(String::new()));
+    }
+
+    #[test]
+    fn unused_nonterminals() {
+        let g = "expr := term | expr '+' term ;\nterm := 'x' ;\nold := term ;\nalso := old ;";
+        let grammar = ParserBuilder::default().into_grammar("expr", g).unwrap();
+        assert_eq!(lint(&grammar), vec!["unused nonterminal also", "unused nonterminal old"]);
+        let grammar = ParserBuilder::default().into_grammar("also", g).unwrap();
+        assert_eq!(lint(&grammar), vec!["unused nonterminal expr"]);
+        // generated helpers aren't reported on their own
+        let g = "list := item* ;\nitem := 'x' ;\nold := (item | 'y')+ ;";
+        let grammar = ParserBuilder::default().into_grammar("list", g).unwrap();
+        assert_eq!(lint(&grammar), vec!["unused nonterminal old"]);
+    }
+
+    #[test]
+    fn included_files() {
+        let dir = std::env::temp_dir().join(format!("abackus-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("item.ebnf"), "item := 'x' ;").unwrap();
+        let grammar = dir.join("list.ebnf");
+        std::fs::write(&grammar, "@include \"item.ebnf\"\nlist := item* ;").unwrap();
+        let options = args(&format!("-t words {} list", grammar.display())).unwrap();
+        let mut included = Vec::new();
+        run(&options, "x x", &mut included).unwrap();
+        assert_eq!(included, vec![dir.join("item.ebnf")]);
+        assert_eq!(modified(&options.grammar, &included).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
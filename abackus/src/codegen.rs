@@ -0,0 +1,232 @@
+#![deny(warnings)]
+
+// Build-time code generation: turn an EBNF spec into Rust source that adds
+// its symbols and rules to a GrammarBuilder directly, so programs don't
+// parse EBNF at startup and grammar errors fail the build.
+//
+// Generated code uses `earlgrey` (and `lexers` for /regex/ terminals),
+// which the including crate needs as dependencies.
+
+use crate::ebnf::{EbnfError, ParserBuilder};
+use crate::lexicon::spec_terminals;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+// Trait method name for the idx-th rule of head
+fn method(head: &str, idx: usize) -> String {
+    let name: String = head.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    match head.strip_prefix('@') {
+        Some(_) => format!("tag{}_{}", name, idx),
+        None => format!("{}_{}", name, idx),
+    }
+}
+
+/// Rust source for the grammar in `spec`. Terminals named in `plugged`
+/// aren't defined by the spec, callers plug them into the GrammarBuilder
+/// passed to the generated `grammar_builder` function, other names without
+/// rules are an `EbnfError::Undefined`. The source has:
+///  - `START` the start symbol
+///  - `grammar_builder(gb)` adds all symbols and rules to `gb`
+///  - `Actions<T>` a trait with one method per rule
+///  - `forest(&actions)` an EarleyForest calling those methods
+pub fn generate(spec: &str, start: &str, plugged: &[&str])
+        -> Result<String, EbnfError> {
+    Ok(generate_with(ParserBuilder::default(), spec, start, plugged)?.0)
+}
+
+// Generated source and the files included by spec
+fn generate_with(mut builder: ParserBuilder, spec: &str, start: &str,
+                 plugged: &[&str]) -> Result<(String, Vec<PathBuf>), EbnfError> {
+    for name in plugged {
+        builder = builder.plug_terminal(*name, |_| false);
+    }
+    let mut parsed = builder.parse_included(spec)?;
+    // names that are neither defined nor plugged are likely missing from
+    // plugged, fail instead of generating nonterminals that never match
+    if !parsed.warnings.is_empty() {
+        return Err(parsed.warnings.remove(0));
+    }
+    let grammar = parsed.gb.into_grammar(start).map_err(EbnfError::Grammar)?;
+    let text: Vec<_> = parsed.specs.iter().map(|s| s.spec.as_str()).collect();
+    let (literals, patterns) = spec_terminals(&text.join("\n"));
+    let included = parsed.specs.into_iter().filter_map(|s| s.path).collect();
+
+    let mut nonterms = Vec::new();
+    let mut terminals = Vec::new();
+    for rule in &grammar.rules {
+        if !nonterms.contains(&rule.head.as_str()) {
+            nonterms.push(rule.head.as_str());
+        }
+        for symbol in &rule.spec {
+            if let Some((name, _)) = symbol.terminal() {
+                if !terminals.contains(&name) && !plugged.contains(&name) {
+                    terminals.push(name);
+                }
+            }
+        }
+    }
+    // writing to a String can't fail
+    let mut out = String::new();
+    let o = &mut out;
+    writeln!(o, "// Generated by abackus::codegen, do not edit.").unwrap();
+    writeln!(o).unwrap();
+    writeln!(o, "#[allow(dead_code)]").unwrap();
+    writeln!(o, "pub const START: &str = {:?};", start).unwrap();
+    writeln!(o).unwrap();
+    writeln!(o, "/// Add the grammar's symbols and rules to `gb`.").unwrap();
+    if !plugged.is_empty() {
+        writeln!(o, "/// Terminals {} must be plugged into `gb` first.",
+                 plugged.join(", ")).unwrap();
+    }
+    writeln!(o, "#[allow(dead_code)]").unwrap();
+    writeln!(o, "pub fn grammar_builder(mut gb: ::earlgrey::GrammarBuilder)").unwrap();
+    writeln!(o, "        -> ::earlgrey::GrammarBuilder {{").unwrap();
+    for nonterm in &nonterms {
+        writeln!(o, "    gb.quiet_nonterm({:?});", nonterm).unwrap();
+    }
+    for terminal in terminals {
+        if patterns.contains(terminal) {
+            // the spec was parsed so its patterns are valid
+            let pattern = match terminal.strip_prefix('/') {
+                Some(p) => &p[..p.len() - 1],
+                None => terminal,
+            };
+            writeln!(o, "    let re = ::lexers::Regex::new({:?}).unwrap();", pattern).unwrap();
+            writeln!(o, "    gb.quiet_terminal({:?}, move |s| re.is_match(s));",
+                     terminal).unwrap();
+        } else if literals.contains(terminal) {
+            writeln!(o, "    gb.quiet_terminal({:?}, |s| s == {:?});",
+                     terminal, terminal).unwrap();
+        } else {
+            return Err(EbnfError::Grammar(format!(
+                "Terminal {} should be listed as plugged", terminal)));
+        }
+    }
+    for rule in &grammar.rules {
+        let spec: Vec<_> = rule.spec.iter().map(|s| format!("{:?}", s.name())).collect();
+        // empty rules need the slice type spelled out
+        let spec = match spec.is_empty() {
+            true => "&[] as &[&str]".to_string(),
+            false => format!("&[{}]", spec.join(", ")),
+        };
+        writeln!(o, "    gb.quiet_rule({:?}, {});", rule.head, spec).unwrap();
+    }
+    writeln!(o, "    gb").unwrap();
+    writeln!(o, "}}").unwrap();
+    writeln!(o).unwrap();
+
+    // one method per rule, helpers share a single one
+    let mut counts = HashMap::new();
+    let mut actions = Vec::new();
+    for rule in &grammar.rules {
+        let rule_str = rule.to_string();
+        if rule.head.starts_with("<Uniq-") {
+            actions.push((rule_str, None));
+            continue;
+        }
+        let idx = counts.entry(rule.head.as_str()).or_insert(0);
+        actions.push((rule_str, Some(method(&rule.head, *idx))));
+        *idx += 1;
+    }
+    writeln!(o, "/// Semantic actions for each rule of the grammar").unwrap();
+    writeln!(o, "#[allow(dead_code, non_snake_case)]").unwrap();
+    writeln!(o, "pub trait Actions<T> {{").unwrap();
+    writeln!(o, "    /// Value for a terminal matching `lexeme`").unwrap();
+    writeln!(o, "    fn terminal(&self, symbol: &str, lexeme: &str) -> T;").unwrap();
+    writeln!(o, "    /// Rules of helpers for groups, optionals and repetitions").unwrap();
+    writeln!(o, "    fn helper(&self, rule: &str, args: Vec<T>) -> T;").unwrap();
+    for (rule, method) in &actions {
+        if let Some(method) = method {
+            writeln!(o, "    /// {}", rule).unwrap();
+            writeln!(o, "    fn {}(&self, args: Vec<T>) -> T;", method).unwrap();
+        }
+    }
+    writeln!(o, "}}").unwrap();
+    writeln!(o).unwrap();
+    writeln!(o, "/// Evaluator calling `actions` for each rule").unwrap();
+    writeln!(o, "#[allow(dead_code)]").unwrap();
+    writeln!(o, "pub fn forest<'a, T: Clone, A: Actions<T>>(actions: &'a A)").unwrap();
+    writeln!(o, "        -> ::earlgrey::EarleyForest<'a, T> {{").unwrap();
+    writeln!(o, "    let mut forest = ::earlgrey::EarleyForest::new(").unwrap();
+    writeln!(o, "        move |symbol, lexeme| actions.terminal(symbol, lexeme));").unwrap();
+    for (rule, method) in &actions {
+        match method {
+            Some(method) => writeln!(o,
+                "    forest.action({:?}, move |args| actions.{}(args));",
+                rule, method).unwrap(),
+            None => writeln!(o,
+                "    forest.action({:?}, move |args| actions.helper({:?}, args));",
+                rule, rule).unwrap(),
+        }
+    }
+    writeln!(o, "    forest").unwrap();
+    writeln!(o, "}}").unwrap();
+    Ok((out, included))
+}
+
+/// For build scripts: generate code for the EBNF file at `input` into
+/// `$OUT_DIR/<name>.rs`, returning its path. Include it with
+/// `include!(concat!(env!("OUT_DIR"), "/<name>.rs"))`. Files included
+/// with `@include` are also looked up next to `input`.
+pub fn generate_file<P: AsRef<Path>>(input: P, start: &str, plugged: &[&str])
+        -> Result<PathBuf, String> {
+    let input = input.as_ref();
+    println!("cargo:rerun-if-changed={}", input.display());
+    let spec = std::fs::read_to_string(input)
+        .map_err(|e| format!("Can't read {}: {}", input.display(), e))?;
+    let mut builder = ParserBuilder::default();
+    if let Some(dir) = input.parent() {
+        builder = builder.include_dir(dir);
+    }
+    let (source, included) = generate_with(builder, &spec, start, plugged)
+        .map_err(|e| format!("{}: {}", input.display(), e))?;
+    for path in included {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+    let out_dir = std::env::var("OUT_DIR")
+        .map_err(|_| "OUT_DIR isn't set, call from build.rs".to_string())?;
+    let name = input.file_stem()
+        .ok_or_else(|| format!("Bad grammar path {}", input.display()))?;
+    let output = Path::new(&out_dir).join(name).with_extension("rs");
+    std::fs::write(&output, source)
+        .map_err(|e| format!("Can't write {}: {}", output.display(), e))?;
+    Ok(output)
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+
+    #[test]
+    fn generated_source() {
+        let g = r#"
+            expr := expr [+-] num | num | '(' expr ')'* ;
+        "#;
+        let source = generate(g, "expr", &["num"]).unwrap();
+        for line in [
+            "pub const START: &str = \"expr\";",
+            "/// Terminals num must be plugged into `gb` first.",
+            "    gb.quiet_nonterm(\"expr\");",
+            "    gb.quiet_nonterm(\"<Uniq-5>\");",
+            "    let re = ::lexers::Regex::new(\"[+-]\").unwrap();",
+            "    gb.quiet_terminal(\"[+-]\", move |s| re.is_match(s));",
+            "    gb.quiet_terminal(\"(\", |s| s == \"(\");",
+            "    gb.quiet_rule(\"expr\", &[\"expr\", \"[+-]\", \"num\"]);",
+            "    /// expr -> expr [+-] num",
+            "    fn expr_0(&self, args: Vec<T>) -> T;",
+            "    fn expr_2(&self, args: Vec<T>) -> T;",
+            "    gb.quiet_rule(\"<Uniq-5>\", &[] as &[&str]);",
+            "    forest.action(\"expr -> num\", move |args| actions.expr_1(args));",
+            "    forest.action(\"<Uniq-5> -> \", move |args| actions.helper(\"<Uniq-5> -> \", args));",
+        ] {
+            assert!(source.lines().any(|l| l == line), "missing {:?} in\n{}", line, source);
+        }
+        assert!(generate(g, "expr", &[]).is_err());
+        assert!(generate("expr := ;", "expr", &[]).is_err());
+    }
+}
@@ -0,0 +1,698 @@
+#![deny(warnings)]
+
+// Front-ends for other grammar notations. Specs are translated into the
+// `:=` dialect so every notation builds the same GrammarBuilder.
+//
+// ABNF (RFC 5234, RFC 7405) describes characters: translated grammars
+// expect one token per input char. Rulenames are case-insensitive so they
+// become lowercase with '-' replaced by '_' (see Dialect::nonterm).
+//
+// ISO 14977 terminal strings stay whole tokens, as in the `:=` dialect.
+// Meta identifiers made of several words are joined with '_'.
+
+use crate::ebnf::EbnfError;
+use std::collections::HashSet;
+
+/// Grammar notations understood by `Dialect::translate`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Dialect {
+    /// The native dialect: `rule := a b | c ;`
+    Ebnf,
+    /// RFC 5234 ABNF: `rule = a b / c`
+    Abnf,
+    /// ISO 14977 EBNF: `rule = a , b | c ;`
+    Iso14977,
+}
+
+impl Dialect {
+    /// Translate a spec in this notation into the `:=` dialect. Errors found
+    /// later, when building a parser out of the translation, have the line
+    /// and column of the translated spec.
+    pub fn translate(self, spec: &str) -> Result<String, EbnfError> {
+        match self {
+            Dialect::Ebnf => Ok(spec.to_string()),
+            Dialect::Abnf => abnf(spec),
+            Dialect::Iso14977 => Iso(Cursor::new(spec)).syntax(),
+        }
+    }
+
+    /// Name of the non-terminal a rule of this notation translates to
+    pub fn nonterm(self, name: &str) -> String {
+        match self {
+            Dialect::Ebnf => name.to_string(),
+            Dialect::Abnf => name.to_lowercase().replace('-', "_"),
+            Dialect::Iso14977 =>
+                name.split_whitespace().collect::<Vec<_>>().join("_"),
+        }
+    }
+}
+
+// Char cursor over a spec that reports errors like EbnfError::Syntax
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Cursor {
+    fn new(spec: &str) -> Cursor {
+        Cursor{chars: spec.chars().collect(), pos: 0}
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).cloned()
+    }
+
+    fn accept(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_digit(radix)) {
+            digits.push(c);
+            self.pos += 1;
+        }
+        digits
+    }
+
+    fn error(&self, expected: &[&str]) -> EbnfError {
+        let before = &self.chars[..self.pos];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        EbnfError::Syntax{
+            line, column,
+            found: self.peek().map(|c| c.to_string()),
+            expected: expected.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+}
+
+// An atom matching a single char
+fn char_atom(c: char) -> String {
+    match c {
+        '\'' => "\"'\"".to_string(),
+        c if c.is_control() => format!("/\\x{{{:x}}}/", c as u32),
+        c => format!("'{}'", c),
+    }
+}
+
+// Postfix repetition for an atom, max None is unbounded
+fn repeat(atom: String, min: usize, max: Option<usize>) -> String {
+    match (min, max) {
+        (0, None) => format!("{{ {} }}", atom),
+        (0, Some(1)) => format!("[ {} ]", atom),
+        (1, None) => format!("( {} )+", atom),
+        (1, Some(1)) => atom,
+        (min, None) => format!("( {} ){{{},}}", atom, min),
+        (min, Some(max)) if min == max => format!("( {} ){{{}}}", atom, min),
+        (min, Some(max)) => format!("( {} ){{{},{}}}", atom, min, max),
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// ABNF
+
+// RFC 5234 Appendix B, added when a grammar uses them without defining them
+const ABNF_CORE: &[(&str, &str)] = &[
+    ("alpha", "ALPHA = %x41-5A / %x61-7A"),
+    ("bit", "BIT = \"0\" / \"1\""),
+    ("char", "CHAR = %x01-7F"),
+    ("cr", "CR = %x0D"),
+    ("crlf", "CRLF = CR LF"),
+    ("ctl", "CTL = %x00-1F / %x7F"),
+    ("digit", "DIGIT = %x30-39"),
+    ("dquote", "DQUOTE = %x22"),
+    ("hexdig", "HEXDIG = DIGIT / \"A\" / \"B\" / \"C\" / \"D\" / \"E\" / \"F\""),
+    ("htab", "HTAB = %x09"),
+    ("lf", "LF = %x0A"),
+    ("lwsp", "LWSP = *(WSP / CRLF WSP)"),
+    ("octet", "OCTET = %x00-FF"),
+    ("sp", "SP = %x20"),
+    ("vchar", "VCHAR = %x21-7E"),
+    ("wsp", "WSP = SP / HTAB"),
+];
+
+fn abnf(spec: &str) -> Result<String, EbnfError> {
+    let mut parser = Abnf{cursor: Cursor::new(spec), defined: HashSet::new(),
+                          used: HashSet::new()};
+    let mut rules = parser.rulelist()?;
+    // pull in core rules until all references are defined
+    loop {
+        let missing: Vec<_> = ABNF_CORE.iter()
+            .filter(|(name, _)| parser.used.contains(*name) &&
+                                !parser.defined.contains(*name))
+            .collect();
+        if missing.is_empty() {
+            return Ok(rules.join("\n"));
+        }
+        for (_, core) in missing {
+            parser.cursor = Cursor::new(core);
+            rules.extend(parser.rulelist()?);
+        }
+    }
+}
+
+struct Abnf {
+    cursor: Cursor,
+    defined: HashSet<String>,
+    used: HashSet<String>,
+}
+
+impl Abnf {
+    // Skip whitespace and comments, newlines only when the rule continues
+    fn skip_cwsp(&mut self) {
+        let c = &mut self.cursor;
+        loop {
+            match c.peek() {
+                Some(' ') | Some('\t') => c.pos += 1,
+                Some(';') => while c.peek().is_some_and(|c| c != '\n') {
+                    c.pos += 1;
+                },
+                Some('\r') if c.peek_at(1) == Some('\n') => c.pos += 1,
+                Some('\n') if matches!(c.peek_at(1), Some(' ') | Some('\t')) =>
+                    c.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    // Skip blank lines and comment lines between rules
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_cwsp();
+            if !self.cursor.accept('\n') {
+                return;
+            }
+        }
+    }
+
+    fn rulelist(&mut self) -> Result<Vec<String>, EbnfError> {
+        let mut rules = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.cursor.peek().is_none() {
+                return Ok(rules);
+            }
+            rules.push(self.rule()?);
+        }
+    }
+
+    fn rulename(&mut self) -> Option<String> {
+        let c = &mut self.cursor;
+        if !c.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let mut name = String::new();
+        while let Some(n) = c.peek()
+                .filter(|&n| n.is_ascii_alphanumeric() || n == '-') {
+            name.push(n);
+            c.pos += 1;
+        }
+        Some(Dialect::Abnf.nonterm(&name))
+    }
+
+    fn rule(&mut self) -> Result<String, EbnfError> {
+        let name = self.rulename()
+            .ok_or_else(|| self.cursor.error(&["<rulename>"]))?;
+        self.skip_cwsp();
+        if !self.cursor.accept('=') {
+            return Err(self.cursor.error(&["=", "=/"]));
+        }
+        // incremental alternatives are just more rules for the name
+        self.cursor.accept('/');
+        self.defined.insert(name.clone());
+        let body = self.alternation()?
+            .ok_or_else(|| self.cursor.error(&["<element>"]))?;
+        self.skip_cwsp();
+        if self.cursor.peek().is_some() && !self.cursor.accept('\n') {
+            return Err(self.cursor.error(&["/", "<element>", "<newline>"]));
+        }
+        Ok(format!("{} := {} ;", name, body))
+    }
+
+    // None when every alternative only matches the empty string, like ""
+    fn alternation(&mut self) -> Result<Option<String>, EbnfError> {
+        let mut alternatives = Vec::new();
+        let mut empty = false;
+        loop {
+            match self.concatenation()? {
+                Some(concatenation) => alternatives.push(concatenation),
+                None => empty = true,
+            }
+            if !self.cursor.accept('/') {
+                break;
+            }
+        }
+        let body = alternatives.join(" | ");
+        Ok(match (alternatives.is_empty(), empty) {
+            (true, _) => None,
+            (false, true) => Some(format!("[ {} ]", body)),
+            (false, false) => Some(body),
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Option<String>, EbnfError> {
+        let mut items = Vec::new();
+        let mut elements = 0;
+        loop {
+            self.skip_cwsp();
+            match self.cursor.peek() {
+                None | Some('/') | Some(')') | Some(']') | Some('\n') => break,
+                _ => {
+                    elements += 1;
+                    items.extend(self.repetition()?);
+                }
+            }
+        }
+        if elements == 0 {
+            return Err(self.cursor.error(&["<element>"]));
+        }
+        Ok(match items.is_empty() {
+            true => None,
+            false => Some(items.join(" ")),
+        })
+    }
+
+    fn repetition(&mut self) -> Result<Option<String>, EbnfError> {
+        let c = &mut self.cursor;
+        let min = c.digits(10);
+        let (min, max) = if c.accept('*') {
+            let max = c.digits(10);
+            (min.parse().unwrap_or(0), max.parse().ok())
+        } else if min.is_empty() {
+            return self.element();
+        } else {
+            let count = min.parse().unwrap_or(usize::MAX);
+            (count, Some(count))
+        };
+        Ok(self.element()?.map(|element| repeat(element, min, max)))
+    }
+
+    fn element(&mut self) -> Result<Option<String>, EbnfError> {
+        if let Some(name) = self.rulename() {
+            self.used.insert(name.clone());
+            return Ok(Some(name));
+        }
+        let c = &mut self.cursor;
+        let close = match c.peek() {
+            Some('(') => ')',
+            Some('[') => ']',
+            Some('"') => return self.char_val(false),
+            Some('%') => return self.percent_val(),
+            Some('<') => return Err(c.error(&["<element>"])),
+            _ => return Err(c.error(&["<rulename>", "(", "[", "\"", "%"])),
+        };
+        c.pos += 1;
+        let body = self.alternation()?;
+        self.skip_cwsp();
+        if !self.cursor.accept(close) {
+            return Err(self.cursor.error(&["/", &close.to_string()]));
+        }
+        Ok(body.map(|body| match close {
+            ')' => format!("( {} )", body),
+            _ => format!("[ {} ]", body),
+        }))
+    }
+
+    // "quoted" strings, case-insensitive unless prefixed by %s. None for
+    // "", it matches the empty string
+    fn char_val(&mut self, sensitive: bool) -> Result<Option<String>, EbnfError> {
+        let c = &mut self.cursor;
+        c.pos += 1; // opening quote
+        let mut chars = Vec::new();
+        while let Some(n) = c.peek().filter(|&n| n != '"' && n != '\n') {
+            chars.push(match n {
+                n if !sensitive && n.is_ascii_alphabetic() => format!(
+                    "/[{}{}]/", n.to_ascii_lowercase(), n.to_ascii_uppercase()),
+                n => char_atom(n),
+            });
+            c.pos += 1;
+        }
+        if !c.accept('"') {
+            return Err(c.error(&["\""]));
+        }
+        Ok(match chars.len() {
+            0 => None,
+            1 => Some(chars.remove(0)),
+            _ => Some(format!("( {} )", chars.join(" "))),
+        })
+    }
+
+    // %x41, %x41.42.43, %x41-5A, %d65, %b1000001, %s"..." and %i"..."
+    fn percent_val(&mut self) -> Result<Option<String>, EbnfError> {
+        let c = &mut self.cursor;
+        c.pos += 1;
+        let radix = match c.peek() {
+            Some('x') | Some('X') => 16,
+            Some('d') | Some('D') => 10,
+            Some('b') | Some('B') => 2,
+            Some('s') | Some('S') | Some('i') | Some('I') => {
+                let sensitive = c.peek().is_some_and(|s| s == 's' || s == 'S');
+                c.pos += 1;
+                if c.peek() != Some('"') {
+                    return Err(c.error(&["\""]));
+                }
+                return self.char_val(sensitive);
+            },
+            _ => return Err(c.error(&["x", "d", "b", "s", "i"])),
+        };
+        c.pos += 1;
+        let value = |c: &mut Cursor| {
+            let digits = c.digits(radix);
+            u32::from_str_radix(&digits, radix).ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(|| c.error(&["<digits>"]))
+        };
+        let first = value(c)?;
+        if c.accept('-') {
+            let last = value(c)?;
+            if last < first {
+                return Err(c.error(&["<range>"]));
+            }
+            return Ok(Some(format!("/[\\x{{{:x}}}-\\x{{{:x}}}]/",
+                                   first as u32, last as u32)));
+        }
+        let mut chars = vec![char_atom(first)];
+        while c.accept('.') {
+            chars.push(char_atom(value(c)?));
+        }
+        Ok(Some(match chars.len() {
+            1 => chars.remove(0),
+            _ => format!("( {} )", chars.join(" ")),
+        }))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// ISO 14977
+
+struct Iso(Cursor);
+
+impl Iso {
+    // Skip whitespace and (* nested comments *)
+    fn skip(&mut self) {
+        let c = &mut self.0;
+        let mut depth = 0;
+        while let Some(n) = c.peek() {
+            if n == '(' && c.peek_at(1) == Some('*') {
+                depth += 1;
+                c.pos += 2;
+            } else if depth > 0 && n == '*' && c.peek_at(1) == Some(')') {
+                depth -= 1;
+                c.pos += 2;
+            } else if depth > 0 || n.is_whitespace() {
+                c.pos += 1;
+            } else {
+                return;
+            }
+        }
+    }
+
+    // Accept a symbol or its alternative representation
+    fn symbol(&mut self, options: &[&str]) -> bool {
+        self.skip();
+        for option in options {
+            let len = option.chars().count();
+            if self.0.chars[self.0.pos..].iter().take(len).cloned()
+                    .eq(option.chars()) {
+                self.0.pos += len;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn syntax(&mut self) -> Result<String, EbnfError> {
+        let mut rules = Vec::new();
+        loop {
+            self.skip();
+            if self.0.peek().is_none() {
+                return Ok(rules.join("\n"));
+            }
+            let name = self.meta_identifier()
+                .ok_or_else(|| self.0.error(&["<meta identifier>"]))?;
+            if !self.symbol(&["="]) {
+                return Err(self.0.error(&["="]));
+            }
+            let body = self.definitions_list()?
+                .ok_or_else(|| self.0.error(&["<definition>"]))?;
+            if !self.symbol(&[";", "."]) {
+                return Err(self.0.error(&["|", ",", ";"]));
+            }
+            rules.push(format!("{} := {} ;", name, body));
+        }
+    }
+
+    fn meta_identifier(&mut self) -> Option<String> {
+        self.skip();
+        let c = &mut self.0;
+        if !c.peek().is_some_and(|c| c.is_alphabetic()) {
+            return None;
+        }
+        let mut words = vec![String::new()];
+        while let Some(n) = c.peek() {
+            if n.is_alphanumeric() || n == '_' {
+                words.last_mut().unwrap().push(n);
+            } else if n == ' ' || n == '\t' {
+                // words only continue with a letter or digit
+                if !words.last().unwrap().is_empty() {
+                    words.push(String::new());
+                }
+            } else {
+                break;
+            }
+            c.pos += 1;
+        }
+        words.retain(|w| !w.is_empty());
+        Some(words.join("_"))
+    }
+
+    // None when every alternative is empty
+    fn definitions_list(&mut self) -> Result<Option<String>, EbnfError> {
+        let mut alternatives = Vec::new();
+        let mut empty = false;
+        loop {
+            match self.single_definition()? {
+                Some(definition) => alternatives.push(definition),
+                None => empty = true,
+            }
+            // '/' separates alternatives unless it closes a '(/'
+            self.skip();
+            let closing = self.0.peek() == Some('/') && self.0.peek_at(1) == Some(')');
+            if closing || !self.symbol(&["|", "/", "!"]) {
+                break;
+            }
+        }
+        let body = alternatives.join(" | ");
+        Ok(match (alternatives.is_empty(), empty) {
+            (true, _) => None,
+            (false, true) => Some(format!("[ {} ]", body)),
+            (false, false) => Some(body),
+        })
+    }
+
+    fn single_definition(&mut self) -> Result<Option<String>, EbnfError> {
+        let mut terms = Vec::new();
+        loop {
+            terms.extend(self.syntactic_term()?);
+            if !self.symbol(&[","]) {
+                break;
+            }
+        }
+        Ok(match terms.is_empty() {
+            true => None,
+            false => Some(terms.join(" ")),
+        })
+    }
+
+    fn syntactic_term(&mut self) -> Result<Option<String>, EbnfError> {
+        let factor = self.syntactic_factor()?;
+        self.skip();
+        if self.0.peek() == Some('-') {
+            // exceptions aren't context-free in general
+            return Err(self.0.error(&[",", "|", ";"]));
+        }
+        Ok(factor)
+    }
+
+    fn syntactic_factor(&mut self) -> Result<Option<String>, EbnfError> {
+        self.skip();
+        let count = self.0.digits(10);
+        if count.is_empty() {
+            return self.syntactic_primary();
+        }
+        if !self.symbol(&["*"]) {
+            return Err(self.0.error(&["*"]));
+        }
+        let count = count.parse().unwrap_or(usize::MAX);
+        Ok(self.syntactic_primary()?
+            .map(|primary| repeat(primary, count, Some(count))))
+    }
+
+    fn syntactic_primary(&mut self) -> Result<Option<String>, EbnfError> {
+        if let Some(name) = self.meta_identifier() {
+            return Ok(Some(name));
+        }
+        let brackets = [
+            (&["[", "(/"], &["]", "/)"], "[ {} ]"),
+            (&["{", "(:"], &["}", ":)"], "{ {} }"),
+            (&["(", "("], &[")", ")"], "( {} )"),
+        ];
+        self.skip();
+        for (open, close, template) in brackets.iter() {
+            // '(' must not take the start of '(/' or '(:'
+            if *template == "( {} )" && matches!(self.0.peek_at(1), Some('/') | Some(':')) {
+                continue;
+            }
+            if !self.symbol(*open) {
+                continue;
+            }
+            let body = self.definitions_list()?;
+            if !self.symbol(*close) {
+                return Err(self.0.error(&["|", ",", close[0]]));
+            }
+            return Ok(body.map(|body| template.replace("{}", &body)));
+        }
+        let c = &mut self.0;
+        match c.peek() {
+            Some(q) if q == '\'' || q == '"' => {
+                c.pos += 1;
+                let mut terminal = String::new();
+                while let Some(n) = c.peek().filter(|&n| n != q && n != '\n') {
+                    terminal.push(n);
+                    c.pos += 1;
+                }
+                if terminal.is_empty() || !c.accept(q) {
+                    return Err(c.error(&[&q.to_string()]));
+                }
+                Ok(Some(format!("{}{}{}", q, terminal, q)))
+            },
+            // special sequences ? ... ? have no meaning outside the spec
+            Some('?') => Err(c.error(&["<primary>"])),
+            _ => Ok(None),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::Dialect;
+    use crate::{EbnfError, ParserBuilder};
+
+    fn chars(input: &str) -> impl Iterator<Item=String> + '_ {
+        input.chars().map(|c| c.to_string())
+    }
+
+    #[test]
+    fn abnf_translation() {
+        let spec = concat!(
+            "; a comment line\r\n",
+            "date-time = date \"T\" time ; trailing comment\r\n",
+            "date  = 4DIGIT \"-\" 2DIGIT [\"-\" 1*2DIGIT]\r\n",
+            "time  = 2DIGIT %x3A.3A 2DIGIT\r\n",
+            "        / %s\"noon\"\r\n",
+            "time  =/ %d48-57 *ALPHA\r\n",
+        );
+        let ebnf = Dialect::Abnf.translate(spec).unwrap();
+        assert_eq!(ebnf.lines().collect::<Vec<_>>(), vec![
+            "date_time := date /[tT]/ time ;",
+            "date := ( digit ){4} '-' ( digit ){2} [ '-' ( digit ){1,2} ] ;",
+            "time := ( digit ){2} ( ':' ':' ) ( digit ){2} | ( 'n' 'o' 'o' 'n' ) ;",
+            "time := /[\\x{30}-\\x{39}]/ { alpha } ;",
+            "alpha := /[\\x{41}-\\x{5a}]/ | /[\\x{61}-\\x{7a}]/ ;",
+            "digit := /[\\x{30}-\\x{39}]/ ;",
+        ]);
+        let parser = ParserBuilder::default()
+            .treeficator(&ebnf, &Dialect::Abnf.nonterm("Date-Time")).unwrap();
+        assert!(parser(chars("2024-01-5t12::30")).is_ok());
+        assert!(parser(chars("2024-1-5T12::30")).is_err());
+        assert!(parser(chars("2024-01-05tnoon")).is_ok());
+        assert!(parser(chars("2024-01-05tNOON")).is_err());
+    }
+
+    #[test]
+    fn abnf_core_rules() {
+        let ebnf = Dialect::Abnf.translate("line = *WSP 1*VCHAR CRLF\n").unwrap();
+        let parser = ParserBuilder::default()
+            .treeficator(&ebnf, "line").unwrap();
+        assert!(parser(chars(" \tGET /\r\n")).is_err());
+        assert!(parser(chars(" \tGET\r\n")).is_ok());
+        assert!(parser(chars("\x01\r\n")).is_err());
+    }
+
+    #[test]
+    fn abnf_empty_string() {
+        let spec = "a = \"\" / %x62 \"\" *\"\" DIGIT\nb = ( \"\" ) \"c\"\n";
+        let ebnf = Dialect::Abnf.translate(spec).unwrap();
+        assert_eq!(ebnf.lines().take(2).collect::<Vec<_>>(), vec![
+            "a := [ 'b' digit ] ;",
+            "b := /[cC]/ ;",
+        ]);
+        let parser = ParserBuilder::default().treeficator(&ebnf, "a").unwrap();
+        assert!(parser(chars("b1")).is_ok());
+        assert!(parser(chars("b")).is_err());
+    }
+
+    #[test]
+    fn abnf_errors() {
+        let error = |line, column, found: Option<&str>| EbnfError::Syntax{
+            line, column, found: found.map(|f| f.to_string()),
+            expected: Vec::new()};
+        let tests = [
+            ("a = b\nc", error(2, 2, None)),
+            ("a = b )\n", error(1, 7, Some(")"))),
+            ("a = <prose>\n", error(1, 5, Some("<"))),
+            ("a = %x5A-41\n", error(1, 12, Some("\n"))),
+            ("a = \"\"\n", error(1, 7, Some("\n"))),
+            ("a = (b\n", error(1, 7, Some("\n"))),
+        ];
+        for (spec, expected) in tests.iter() {
+            match Dialect::Abnf.translate(spec) {
+                Err(EbnfError::Syntax{line, column, found, ..}) =>
+                    assert_eq!(error(line, column, found.as_deref()), *expected,
+                               "{:?}", spec),
+                other => panic!("{:?} gave {:?}", spec, other),
+            }
+        }
+    }
+
+    #[test]
+    fn iso_translation() {
+        let spec = r#"
+            (* a (* nested *) comment *)
+            program = 'begin', statement list, "end" .
+            statement list = statement, { ';', statement } ;
+            statement = identifier, ':=', expression | (/ 'skip' /) |;
+            expression = 2 * identifier | (: '+' :), identifier ;
+            identifier = 'x' | 'y' ;
+        "#;
+        let ebnf = Dialect::Iso14977.translate(spec).unwrap();
+        assert_eq!(ebnf.lines().collect::<Vec<_>>(), vec![
+            "program := 'begin' statement_list \"end\" ;",
+            "statement_list := statement { ';' statement } ;",
+            "statement := [ identifier ':=' expression | [ 'skip' ] ] ;",
+            "expression := ( identifier ){2} | { '+' } identifier ;",
+            "identifier := 'x' | 'y' ;",
+        ]);
+        let parser = ParserBuilder::default()
+            .treeficator(&ebnf, "program").unwrap();
+        let input = "begin x := x y ; skip ; ; y := + + x end";
+        assert!(parser(input.split_whitespace()).is_ok());
+        assert!(parser("begin x := end".split_whitespace()).is_err());
+        for bad in ["a = b", "a = b - c ;", "a = ;", "a = 'b ;", "a = [ b ;", "= b ;",
+                    "a = ? b ? ;"] {
+            assert!(matches!(Dialect::Iso14977.translate(bad),
+                             Err(EbnfError::Syntax{..})), "{:?} should fail", bad);
+        }
+    }
+}
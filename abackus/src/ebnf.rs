@@ -1,11 +1,14 @@
 #![deny(warnings)]
 
-use lexers::EbnfTokenizer;
+use lexers::{EbnfTokenizer, Regex};
 use earlgrey::{
     Grammar, GrammarBuilder,
     EarleyParser, EarleyForest,
 };
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::include::{Included, Includes, Parsed};
 
 
 // https://en.wikipedia.org/wiki/Extended_Backus%E2%80%93Naur_form
@@ -21,7 +24,14 @@ pub fn ebnf_grammar() -> Grammar {
                     i == 0 && c == '@' ||
                     i == 1 && c.is_alphabetic() ||
                     i > 1 && (c.is_alphanumeric() || c == '_')))
+      .terminal("<Regex>", move |s|
+                s.len() > 2 && s.starts_with('/') && s.ends_with('/'))
+      .terminal("<Class>", move |s|
+                s.len() > 2 && s.starts_with('[') && s.ends_with(']'))
+      .terminal("<Count>", move |s|
+                !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
       .terminal(":=", |s| s == ":=")
+      .terminal(":", |s| s == ":")
       .terminal(";", |s| s == ";")
       .terminal("[", |s| s == "[")
       .terminal("]", |s| s == "]")
@@ -32,27 +42,45 @@ pub fn ebnf_grammar() -> Grammar {
       .terminal("|", |s| s == "|")
       .terminal("'", |s| s == "'")
       .terminal("\"", |s| s == "\"")
+      .terminal("?", |s| s == "?")
+      .terminal("*", |s| s == "*")
+      .terminal("+", |s| s == "+")
+      .terminal(",", |s| s == ",")
       .nonterm("<RuleList>")
       .nonterm("<Rule>")
       .nonterm("<Body>")
+      .nonterm("<Alt>")
       .nonterm("<Part>")
+      .nonterm("<Item>")
       .nonterm("<Atom>")
       .rule("<RuleList>", &["<RuleList>", "<Rule>"])
       .rule("<RuleList>", &["<Rule>"])
       .rule("<Rule>", &["<Id>", ":=", "<Body>", ";"])
-      .rule("<Body>", &["<Body>", "|", "<Part>"])
-      .rule("<Body>", &["<Part>"])
-      .rule("<Part>", &["<Part>", "<Atom>"])
-      .rule("<Part>", &["<Atom>"])
+      .rule("<Body>", &["<Body>", "|", "<Alt>"])
+      .rule("<Body>", &["<Alt>"])
+      .rule("<Alt>", &["<Part>"])
+      .rule("<Alt>", &["@<Tag>", "<Part>"])
+      .rule("<Part>", &["<Part>", "<Item>"])
+      .rule("<Part>", &["<Item>"])
+      .rule("<Item>", &["<Atom>"])
+      .rule("<Item>", &["<Id>", ":", "<Atom>"])
       .rule("<Atom>", &["<Id>"])
       .rule("<Atom>", &["'", "<Chars>", "'"])
       .rule("<Atom>", &["\"", "<Chars>", "\""])
+      .rule("<Atom>", &["<Regex>"])
+      .rule("<Atom>", &["<Class>"])
       .rule("<Atom>", &["[", "<Body>", "]"])
       .rule("<Atom>", &["{", "<Body>", "}"])
       .rule("<Atom>", &["(", "<Body>", ")"])
       .rule("<Atom>", &["[", "<Body>", "]", "@<Tag>"])
       .rule("<Atom>", &["{", "<Body>", "}", "@<Tag>"])
       .rule("<Atom>", &["(", "<Body>", ")", "@<Tag>"])
+      .rule("<Atom>", &["<Atom>", "?"])
+      .rule("<Atom>", &["<Atom>", "*"])
+      .rule("<Atom>", &["<Atom>", "+"])
+      .rule("<Atom>", &["<Atom>", "{", "<Count>", "}"])
+      .rule("<Atom>", &["<Atom>", "{", "<Count>", ",", "}"])
+      .rule("<Atom>", &["<Atom>", "{", "<Count>", ",", "<Count>", "}"])
       .into_grammar("<RuleList>")
       .expect("Bad EBNF Grammar")
 }
@@ -62,11 +90,117 @@ macro_rules! debug {
 }
 
 
+/// Errors found while building a parser out of an EBNF grammar spec.
+#[derive(Clone,Debug,PartialEq)]
+pub enum EbnfError {
+    /// The spec doesn't follow EBNF syntax. Line and column (1-based) point
+    /// to the offending token (`found` is None at the end of the spec).
+    /// `expected` has the EBNF symbols that would have been valid there.
+    Syntax {
+        line: usize,
+        column: usize,
+        found: Option<String>,
+        expected: Vec<String>,
+    },
+    /// The spec can't be split in tokens at line and column, like a quote
+    /// that starts an unterminated string
+    Token {
+        line: usize,
+        column: usize,
+        error: String,
+    },
+    /// A `/regex/` or `[a-z]` class terminal isn't a valid pattern
+    Pattern {
+        line: usize,
+        column: usize,
+        pattern: String,
+        error: String,
+    },
+    /// A rule body uses `name` but it has no rules of its own and wasn't
+    /// plugged as a terminal. Line and column point to its first use, in the
+    /// spec given to `include` by its `@include` name, None for the main spec.
+    Undefined {
+        line: usize,
+        column: usize,
+        name: String,
+        include: Option<String>,
+    },
+    /// `@include "name"` at line and column can't be found, forms a cycle
+    /// or the included spec has errors of its own
+    Include {
+        line: usize,
+        column: usize,
+        name: String,
+        error: String,
+    },
+    /// The spec is well formed but doesn't make a valid Grammar
+    Grammar(String),
+}
+
+impl fmt::Display for EbnfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EbnfError::Syntax{line, column, found, expected} => {
+                write!(f, "Syntax error at {}:{}: ", line, column)?;
+                match found {
+                    Some(found) => write!(f, "unexpected {:?}", found)?,
+                    None => write!(f, "unexpected end of grammar")?,
+                }
+                if !expected.is_empty() {
+                    write!(f, ", expected one of: {}", expected.join(" "))?;
+                }
+                Ok(())
+            },
+            EbnfError::Token{line, column, error} =>
+                write!(f, "Syntax error at {}:{}: {}", line, column, error),
+            EbnfError::Pattern{line, column, pattern, error} =>
+                write!(f, "Bad pattern {} at {}:{}: {}",
+                       pattern, line, column, error),
+            EbnfError::Undefined{line, column, name, include: None} =>
+                write!(f, "Undefined nonterminal {} at {}:{}", name, line, column),
+            EbnfError::Undefined{line, column, name, include: Some(include)} =>
+                write!(f, "Undefined nonterminal {} in {} at {}:{}",
+                       name, include, line, column),
+            EbnfError::Include{line, column, name, error} =>
+                write!(f, "Include {} at {}:{}: {}", name, line, column, error),
+            EbnfError::Grammar(error) => write!(f, "Grammar error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for EbnfError {}
+
+// Upper bound for counted repetitions like digit{2,4}
+const MAX_COUNT: usize = 100;
+
+// Tokens of a grammar spec with their line/column
+pub(crate) type Tokens = Vec<(String, (usize, usize))>;
+
+// Line/column where a spec ends, Err if the tokenizer got stuck there first
+// with the reason it gave, if any
+type End = Result<(usize, usize), ((usize, usize), Option<String>)>;
+
+// Symbols of a rule alternative with their optional field label
+type Spec = Vec<(Option<String>, String)>;
+
+// A rule alternative with its optional @tag
+type Alt = (Option<String>, Spec);
+
+// Field labels of each labeled rule, "head -> a b" => [Some(lhs), None]
+pub(crate) type Fields = HashMap<String, Vec<Option<String>>>;
+
+// Tag of each tagged alternative without its @, "head -> a b" => tag
+pub(crate) type Tags = HashMap<String, String>;
+
 #[derive(Default)]
-pub struct ParserBuilder(pub GrammarBuilder);
+pub struct ParserBuilder {
+    pub(crate) gb: GrammarBuilder,
+    pub(crate) includes: Includes,
+}
 
 #[derive(Clone,Debug)]
-enum G {Body(Vec<Vec<String>>), Part(Vec<String>), Atom(String), Nop}
+enum G {Body(Vec<Alt>), Alt(Alt), Part(Spec),
+        Item((Option<String>, String)), Atom(String), Nop}
 
 // use to destructure G enum into a specific alternative
 macro_rules! pull {
@@ -78,209 +212,411 @@ macro_rules! pull {
 
 impl ParserBuilder {
     // Parsing terminals / non-terminal leaf nodes
-    fn evaler<'a>(gb: &'a RefCell<GrammarBuilder>) -> EarleyForest<'a, G> {
+    fn evaler<'a>(gb: &'a RefCell<GrammarBuilder>, tokens: &'a Tokens,
+                  errors: &'a RefCell<Vec<EbnfError>>)
+            -> EarleyForest<'a, G> {
         EarleyForest::new(move |symbol, token| {
             match symbol {
                 "<Id>" => {
                     debug!("Adding non-term {:?}", token);
                     gb.borrow_mut().quiet_nonterm(token);
                 },
-                "@<Tag>" => {
-                    debug!("Adding non-term {:?}", token);
-                    gb.borrow_mut().quiet_nonterm(token);
-                },
                 "<Chars>" => {
                     debug!("Adding terminal {:?}", token);
                     let tok = token.to_string();
                     gb.borrow_mut()
                         .quiet_terminal(token, move |s| s == tok);
                 },
+                "<Regex>" | "<Class>" => {
+                    debug!("Adding pattern terminal {:?}", token);
+                    // /regex/ is matched without its slashes
+                    let pattern = match symbol {
+                        "<Regex>" => &token[1..token.len() - 1],
+                        _ => token,
+                    };
+                    match Regex::new(pattern) {
+                        Ok(re) => gb.borrow_mut()
+                            .quiet_terminal(token, move |s| re.is_match(s)),
+                        Err(error) => {
+                            let (line, column) = tokens.iter()
+                                .find(|t| t.0 == token)
+                                .map(|t| t.1)
+                                .expect("BUG: pattern should come from a token");
+                            errors.borrow_mut().push(EbnfError::Pattern{
+                                line, column, pattern: token.to_string(), error
+                            });
+                        }
+                    }
+                },
                 _ => ()
             }
             G::Atom(token.to_string())
         })
     }
 
+    // Add rules for head, remember field labels and tags of rules
+    fn add_rules(gb: &RefCell<GrammarBuilder>, fields: &RefCell<Fields>,
+                 tags: &RefCell<Tags>, head: &str, body: Vec<Alt>) {
+        let mut t_gb = gb.borrow_mut();
+        for (tag, rule) in body {
+            let (labels, spec): (Vec<_>, Vec<_>) = rule.into_iter().unzip();
+            debug!("Adding rule {:?} -> {:?}", head, spec);
+            t_gb.quiet_rule(head, spec.as_slice());
+            let rule = format!("{} -> {}", head, spec.join(" "));
+            if let Some(tag) = tag {
+                tags.borrow_mut().insert(rule.clone(), tag[1..].to_string());
+            }
+            if labels.iter().any(|l| l.is_some()) {
+                fields.borrow_mut().insert(rule, labels);
+            }
+        }
+    }
+
+    // Add a helper non-terminal with the given alternatives. Helpers are
+    // named after their @tag or get a unique name.
+    fn helper(gb: &RefCell<GrammarBuilder>, fields: &RefCell<Fields>,
+              tags: &RefCell<Tags>, tag: Option<String>, body: Vec<Alt>)
+            -> String {
+        let aux = tag.unwrap_or_else(|| gb.borrow().unique_symbol_name());
+        debug!("Adding non-term {:?}", aux);
+        gb.borrow_mut().quiet_nonterm(&aux);
+        ParserBuilder::add_rules(gb, fields, tags, &aux, body);
+        aux
+    }
+
     fn action_rule<'a>(ev: &mut EarleyForest<'a, G>,
-                       gb: &'a RefCell<GrammarBuilder>) {
+                       gb: &'a RefCell<GrammarBuilder>,
+                       fields: &'a RefCell<Fields>,
+                       tags: &'a RefCell<Tags>) {
         ev.action("<Rule> -> <Id> := <Body> ;", move |mut n| {
             let id = pull!(G::Atom, n.remove(0));
             let body = pull!(G::Body, n.remove(1));
-            let mut t_gb = gb.borrow_mut();
-            for rule in body {
-                debug!("Adding rule {:?} -> {:?}", id, rule);
-                t_gb.quiet_rule(&id, rule.as_slice());
-            }
+            ParserBuilder::add_rules(gb, fields, tags, &id, body);
             G::Nop
         });
     }
 
     fn action_body<'a>(ev: &mut EarleyForest<'a, G>) {
-        ev.action("<Body> -> <Body> | <Part>", |mut n| {
+        ev.action("<Body> -> <Body> | <Alt>", |mut n| {
             let mut body = pull!(G::Body, n.remove(0));
-            body.push(pull!(G::Part, n.remove(1)));
+            body.push(pull!(G::Alt, n.remove(1)));
             G::Body(body)
         });
-        ev.action("<Body> -> <Part>", |mut n| {
-            let part = pull!(G::Part, n.remove(0));
-            G::Body(vec!(part))
+        ev.action("<Body> -> <Alt>", |mut n| {
+            let alt = pull!(G::Alt, n.remove(0));
+            G::Body(vec!(alt))
+        });
+        ev.action("<Alt> -> <Part>", |mut n| {
+            G::Alt((None, pull!(G::Part, n.remove(0))))
+        });
+        ev.action("<Alt> -> @<Tag> <Part>", |mut n| {
+            let tag = pull!(G::Atom, n.remove(0));
+            G::Alt((Some(tag), pull!(G::Part, n.remove(0))))
         });
     }
 
     fn action_part<'a>(ev: &mut EarleyForest<'a, G>) {
-        ev.action("<Part> -> <Part> <Atom>", |mut n| {
+        ev.action("<Part> -> <Part> <Item>", |mut n| {
             let mut part = pull!(G::Part, n.remove(0));
-            part.push(pull!(G::Atom, n.remove(0)));
+            part.push(pull!(G::Item, n.remove(0)));
             G::Part(part)
         });
-        ev.action("<Part> -> <Atom>", |mut n| {
-            G::Part(vec!(pull!(G::Atom, n.remove(0))))
+        ev.action("<Part> -> <Item>", |mut n| {
+            G::Part(vec!(pull!(G::Item, n.remove(0))))
+        });
+        ev.action("<Item> -> <Atom>", |mut n| {
+            G::Item((None, pull!(G::Atom, n.remove(0))))
+        });
+        ev.action("<Item> -> <Id> : <Atom>", |mut n| {
+            let label = pull!(G::Atom, n.remove(0));
+            G::Item((Some(label), pull!(G::Atom, n.remove(1))))
         });
     }
 
-    fn action_grouping<'a>(ev: &mut EarleyForest<'a, G>,
-                           gb: &'a RefCell<GrammarBuilder>) {
-        ev.action("<Atom> -> ( <Body> )", move |mut n| {
-            let aux = gb.borrow().unique_symbol_name();
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
-            let body = pull!(G::Body, n.remove(1));
-            for rule in body {
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-            }
-            G::Atom(aux)
+    // Plain and @tagged versions of the rule, tag is passed to the action
+    fn tagged_action<'a, F>(ev: &mut EarleyForest<'a, G>, rule: &str, action: F)
+            where F: 'a + Clone + Fn(Vec<G>, Option<String>) -> G {
+        let plain = action.clone();
+        ev.action(rule, move |n| plain(n, None));
+        ev.action(&format!("{} @<Tag>", rule), move |mut n| {
+            let tag = pull!(G::Atom, n.pop().expect("BUG: missing tag"));
+            action(n, Some(tag))
         });
-        ev.action("<Atom> -> ( <Body> ) @<Tag>", move |mut n| {
-            let aux = pull!(G::Atom, n.remove(3));
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
+    }
+
+    fn action_grouping<'a>(ev: &mut EarleyForest<'a, G>,
+                           gb: &'a RefCell<GrammarBuilder>,
+                           fields: &'a RefCell<Fields>,
+                           tags: &'a RefCell<Tags>) {
+        // <Atom> -> aux ; aux -> <Body> ;
+        ParserBuilder::tagged_action(ev, "<Atom> -> ( <Body> )",
+                                     move |mut n, tag| {
             let body = pull!(G::Body, n.remove(1));
-            for rule in body {
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-            }
-            G::Atom(aux)
+            G::Atom(ParserBuilder::helper(gb, fields, tags, tag, body))
         });
     }
 
     fn action_optional<'a>(ev: &mut EarleyForest<'a, G>,
-                           gb: &'a RefCell<GrammarBuilder>) {
-        ev.action("<Atom> -> [ <Body> ]", move |mut n| {
-            // <Atom> -> aux ; aux -> <e> | <Body> ;
-            let aux = gb.borrow().unique_symbol_name();
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
-            let body = pull!(G::Body, n.remove(1));
-            for rule in body {
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-                debug!("Adding rule {:?} -> []", aux);
-                t_gb.quiet_rule::<_, String>(&aux, &[]);
-            }
-            G::Atom(aux)
+                           gb: &'a RefCell<GrammarBuilder>,
+                           fields: &'a RefCell<Fields>,
+                           tags: &'a RefCell<Tags>) {
+        // <Atom> -> aux ; aux -> <e> | <Body> ;
+        ParserBuilder::tagged_action(ev, "<Atom> -> [ <Body> ]",
+                                     move |mut n, tag| {
+            let mut body = pull!(G::Body, n.remove(1));
+            body.push((None, Vec::new()));
+            G::Atom(ParserBuilder::helper(gb, fields, tags, tag, body))
         });
-        ev.action("<Atom> -> [ <Body> ] @<Tag>", move |mut n| {
-            let aux = pull!(G::Atom, n.remove(3));
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
-            let body = pull!(G::Body, n.remove(1));
-            for rule in body {
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-                debug!("Adding rule {:?} -> []", aux);
-                t_gb.quiet_rule::<_, String>(&aux, &[]);
+    }
+
+    fn action_repeat<'a>(ev: &mut EarleyForest<'a, G>,
+                         gb: &'a RefCell<GrammarBuilder>,
+                         fields: &'a RefCell<Fields>,
+                         tags: &'a RefCell<Tags>) {
+        // <Atom> -> aux ; aux -> <e> | <Body> aux ;
+        ParserBuilder::tagged_action(ev, "<Atom> -> { <Body> }",
+                                     move |mut n, tag| {
+            let aux = tag.unwrap_or_else(|| gb.borrow().unique_symbol_name());
+            let mut body = pull!(G::Body, n.remove(1));
+            for (_, rule) in body.iter_mut() {
+                rule.push((None, aux.clone()));
             }
-            G::Atom(aux)
+            body.push((None, Vec::new()));
+            G::Atom(ParserBuilder::helper(gb, fields, tags, Some(aux), body))
         });
     }
 
-    fn action_repeat<'a>(ev: &mut EarleyForest<'a, G>,
-                         gb: &'a RefCell<GrammarBuilder>) {
-        ev.action("<Atom> -> { <Body> }", move |mut n| {
-            // <Atom> -> aux ; aux -> <e> | <Body> aux ;
+    fn action_postfix<'a>(ev: &mut EarleyForest<'a, G>,
+                          gb: &'a RefCell<GrammarBuilder>,
+                          fields: &'a RefCell<Fields>,
+                          tags: &'a RefCell<Tags>,
+                          errors: &'a RefCell<Vec<EbnfError>>) {
+        // aux names the helper when its rules refer back to it
+        let helper = move |aux: Option<String>, body: Vec<Vec<String>>| {
+            let body = body.into_iter()
+                .map(|rule| (None, rule.into_iter().map(|s| (None, s)).collect()))
+                .collect();
+            G::Atom(ParserBuilder::helper(gb, fields, tags, aux, body))
+        };
+        ev.action("<Atom> -> <Atom> ?", move |mut n| {
+            // <Atom> -> aux ; aux -> <e> | <Atom> ;
+            let atom = pull!(G::Atom, n.remove(0));
+            helper(None, vec![vec![], vec![atom]])
+        });
+        ev.action("<Atom> -> <Atom> *", move |mut n| {
+            // <Atom> -> aux ; aux -> <e> | <Atom> aux ;
+            let atom = pull!(G::Atom, n.remove(0));
             let aux = gb.borrow().unique_symbol_name();
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
-            let body = pull!(G::Body, n.remove(1));
-            for mut rule in body {
-                rule.push(aux.clone());
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-                debug!("Adding rule {:?} -> []", aux);
-                t_gb.quiet_rule::<_, String>(&aux, &[]);
-            }
-            G::Atom(aux)
+            helper(Some(aux.clone()), vec![vec![], vec![atom, aux]])
         });
-        ev.action("<Atom> -> { <Body> } @<Tag>", move |mut n| {
-            // <Atom> -> aux ; aux -> <e> | <Body> aux ;
-            let aux = pull!(G::Atom, n.remove(3));
-            debug!("Adding non-term {:?}", aux);
-            let mut t_gb = gb.borrow_mut();
-            t_gb.quiet_nonterm(&aux);
-            let body = pull!(G::Body, n.remove(1));
-            for mut rule in body {
-                rule.push(aux.clone());
-                debug!("Adding rule {:?} -> {:?}", aux, rule);
-                t_gb.quiet_rule(&aux, rule.as_slice());
-                debug!("Adding rule {:?} -> []", aux);
-                t_gb.quiet_rule::<_, String>(&aux, &[]);
+        ev.action("<Atom> -> <Atom> +", move |mut n| {
+            // <Atom> -> aux ; aux -> <Atom> | <Atom> aux ;
+            let atom = pull!(G::Atom, n.remove(0));
+            let aux = gb.borrow().unique_symbol_name();
+            helper(Some(aux.clone()), vec![vec![atom.clone()], vec![atom, aux]])
+        });
+        // <Atom> -> aux ; aux -> <Atom>{min} | ... | <Atom>{max} ;
+        let counted = move |atom: String, min: String, max: Option<String>| {
+            let min: usize = min.parse().unwrap_or(usize::MAX);
+            let max: Option<usize> = max.map(|m| m.parse().unwrap_or(usize::MAX));
+            if min > MAX_COUNT || max.is_some_and(|m| m < min || m == 0 || m > MAX_COUNT) {
+                errors.borrow_mut().push(EbnfError::Grammar(format!(
+                    "Bad repetition bounds for {}", atom)));
+                return G::Atom(atom);
+            }
+            match max {
+                Some(max) => helper(None, (min..=max)
+                    .map(|count| vec![atom.clone(); count]).collect()),
+                None => {
+                    // unbounded, <Atom>{min} followed by <Atom>*
+                    let aux = gb.borrow().unique_symbol_name();
+                    let star = pull!(G::Atom, helper(
+                        Some(aux.clone()), vec![vec![], vec![atom.clone(), aux]]));
+                    let mut rule = vec![atom; min];
+                    rule.push(star);
+                    helper(None, vec![rule])
+                }
             }
-            G::Atom(aux)
+        };
+        ev.action("<Atom> -> <Atom> { <Count> }", move |mut n| {
+            let atom = pull!(G::Atom, n.remove(0));
+            let count = pull!(G::Atom, n.remove(1));
+            counted(atom, count.clone(), Some(count))
         });
+        ev.action("<Atom> -> <Atom> { <Count> , }", move |mut n| {
+            let atom = pull!(G::Atom, n.remove(0));
+            let min = pull!(G::Atom, n.remove(1));
+            counted(atom, min, None)
+        });
+        ev.action("<Atom> -> <Atom> { <Count> , <Count> }", move |mut n| {
+            let atom = pull!(G::Atom, n.remove(0));
+            let min = pull!(G::Atom, n.remove(1));
+            let max = pull!(G::Atom, n.remove(2));
+            counted(atom, min, Some(max))
+        });
+    }
+
+    // Tokenize the user grammar also returning where it ends
+    fn tokenize(user_grammar_spec: &str) -> (Tokens, End) {
+        let mut tokenizer = EbnfTokenizer::new(user_grammar_spec.chars());
+        let mut tokens = Vec::new();
+        while let Some(token) = tokenizer.next() {
+            tokens.push((token, tokenizer.position()));
+        }
+        let end = tokenizer.position();
+        let end = match tokenizer.exhausted() {
+            true => Ok(end),
+            false => Err((end, tokenizer.error().map(String::from))),
+        };
+        (tokens, end)
+    }
+
+    // Pinpoint why the user grammar can't be parsed as EBNF: the first token
+    // the parser can't take, or where the tokenizer got stuck
+    fn syntax_error(ebnf: &EarleyParser, user_grammar_spec: &str,
+                    tokens: &Tokens, end: End) -> EbnfError {
+        let (idx, expected) = ebnf.diagnose(tokens.iter().map(|t| &t.0))
+            .unwrap_or_else(|| (tokens.len(), Vec::new()));
+        let (found, (line, column)) = match (tokens.get(idx), end) {
+            (Some((token, position)), _) => (Some(token.clone()), *position),
+            (None, Ok(end)) => (None, end),
+            (None, Err(((line, column), Some(error)))) =>
+                return EbnfError::Token{line, column, error},
+            (None, Err(((line, column), None))) => {
+                let found = user_grammar_spec.lines().nth(line - 1)
+                    .and_then(|l| l.chars().nth(column - 1));
+                // quotes only stop the tokenizer when they aren't closed
+                if let Some('\'' | '"') = found {
+                    let error = "unterminated string".to_string();
+                    return EbnfError::Token{line, column, error};
+                }
+                (found.map(|c| c.to_string()), (line, column))
+            }
+        };
+        EbnfError::Syntax{line, column, found, expected}
+    }
+
+    // Identifiers used in rule bodies of all specs without rules that weren't
+    // plugged either, reported once where they're first used
+    pub(crate) fn check_references(gb: &GrammarBuilder, specs: &[Included])
+            -> Vec<EbnfError> {
+        let mut seen = HashSet::new();
+        let mut warnings = Vec::new();
+        for Included{tokens, include, ..} in specs {
+            let next = |idx: usize| tokens.get(idx + 1).map(|t| t.0.as_str());
+            for (idx, (token, (line, column))) in tokens.iter().enumerate() {
+                let id = token.chars().next().is_some_and(|c| c.is_alphabetic()) &&
+                    token.chars().all(|c| c.is_alphanumeric() || c == '_');
+                // skip literal contents, rule heads and field labels
+                let quoted = idx > 0 && matches!(tokens[idx - 1].0.as_str(), "'" | "\"")
+                    && next(idx) == Some(tokens[idx - 1].0.as_str());
+                if !id || quoted || matches!(next(idx), Some(":=") | Some(":")) {
+                    continue;
+                }
+                if !gb.is_defined(token) && seen.insert(token) {
+                    warnings.push(EbnfError::Undefined{
+                        line: *line, column: *column, name: token.clone(),
+                        include: include.clone(),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    // Start from a GrammarBuilder that may already have terminals plugged
+    pub fn new(gb: GrammarBuilder) -> Self {
+        ParserBuilder{gb, includes: Includes::default()}
     }
 
-    // Parse a user grammar into a builder where we can plug terminal matchers
+    // The GrammarBuilder with the terminals plugged so far
+    pub fn into_grammar_builder(self) -> GrammarBuilder {
+        self.gb
+    }
+
+    // Parse a user grammar into a builder where we can plug terminal matchers,
+    // also returning likely mistakes that still make a grammar, like rules
+    // using nonterminals that have no rules (EbnfError::Undefined)
     pub fn parse_grammar(gb: GrammarBuilder, user_grammar_spec: &str)
-            -> Result<GrammarBuilder, String> {
-        let user_grammar_builder = RefCell::new(gb);
+            -> Result<(GrammarBuilder, Vec<EbnfError>), EbnfError> {
+        let parsed = ParserBuilder::new(gb).parse_included(user_grammar_spec)?;
+        Ok((parsed.gb, parsed.warnings))
+    }
+
+    // Parse a user grammar and its includes also returning field labels
+    pub(crate) fn parse_grammar_fields(self, user_grammar_spec: &str)
+            -> Result<(GrammarBuilder, Fields), EbnfError> {
+        let parsed = self.parse_included(user_grammar_spec)?;
+        Ok((parsed.gb, parsed.fields))
+    }
+
+    // Parse a single spec, its @include lines already taken out, adding its
+    // rules, field labels and tags to parsed and returning its tokens
+    pub(crate) fn parse_spec(parsed: &mut Parsed, user_grammar_spec: &str)
+            -> Result<Tokens, EbnfError> {
+        // Build parser for EBNF grammar
+        let ebnf = EarleyParser::new(ebnf_grammar());
+        let (tokens, end) = ParserBuilder::tokenize(user_grammar_spec);
+        // Only look for the syntax error when the parse fails
+        let state = end.as_ref().ok()
+            .and_then(|_| ebnf.parse(tokens.iter().map(|t| &t.0)).ok());
+        let state = state.ok_or_else(|| ParserBuilder::syntax_error(
+            &ebnf, user_grammar_spec, &tokens, end))?;
+        let user_grammar_builder = RefCell::new(std::mem::take(&mut parsed.gb));
+        let errors = RefCell::new(Vec::new());
+        let fields = RefCell::new(Fields::new());
+        let tags = RefCell::new(Tags::new());
         {
-            let mut ev = ParserBuilder::evaler(&user_grammar_builder);
+            let mut ev = ParserBuilder::evaler(&user_grammar_builder,
+                                               &tokens, &errors);
             ev.action("<RuleList> -> <RuleList> <Rule>", |_| G::Nop);
             ev.action("<RuleList> -> <Rule>", |_| G::Nop);
-            ParserBuilder::action_rule(&mut ev, &user_grammar_builder);
+            let gb = &user_grammar_builder;
+            ParserBuilder::action_rule(&mut ev, gb, &fields, &tags);
             ParserBuilder::action_body(&mut ev);
             ParserBuilder::action_part(&mut ev);
-            ParserBuilder::action_grouping(&mut ev, &user_grammar_builder);
-            ParserBuilder::action_optional(&mut ev, &user_grammar_builder);
-            ParserBuilder::action_repeat(&mut ev, &user_grammar_builder);
+            ParserBuilder::action_grouping(&mut ev, gb, &fields, &tags);
+            ParserBuilder::action_optional(&mut ev, gb, &fields, &tags);
+            ParserBuilder::action_repeat(&mut ev, gb, &fields, &tags);
+            ParserBuilder::action_postfix(&mut ev, gb, &fields, &tags, &errors);
             ev.action("<Atom> -> <Id>", |mut n| n.remove(0));
             ev.action("<Atom> -> ' <Chars> '", |mut n| n.remove(1));
             ev.action("<Atom> -> \" <Chars> \"", |mut n| n.remove(1));
-            // Build parser for EBNF grammar
-            let ebnf = EarleyParser::new(ebnf_grammar());
-            // Use EBNF parser to parse the user provided grammar
-            let state = ebnf
-                .parse(EbnfTokenizer::new(user_grammar_spec.chars()))
-                .unwrap_or_else(|_| panic!("Failed to parse user grammar. {}",
-                                           user_grammar_spec));
+            ev.action("<Atom> -> <Regex>", |mut n| n.remove(0));
+            ev.action("<Atom> -> <Class>", |mut n| n.remove(0));
             // Forge user's grammar builder by executing semantic actions
-            if ev.eval_all(&state)?.len() != 1 {
+            if ev.eval_all(&state).map_err(EbnfError::Grammar)?.len() != 1 {
                 panic!("BUG: EBNF grammar shouldn't be ambiguous!");
             }
         }
+        if let Some(error) = errors.into_inner().into_iter().next() {
+            return Err(error);
+        }
         // User's GrammarBuilder has all rules and non-terminals from the spec
-        Ok(user_grammar_builder.into_inner())
+        parsed.gb = user_grammar_builder.into_inner();
+        parsed.fields.extend(fields.into_inner());
+        parsed.tags.extend(tags.into_inner());
+        Ok(tokens)
     }
 
     // Plug-in functions that parse Terminals before we build the grammar
     pub fn plug_terminal<N, F>(mut self, name: N, pred: F) -> Self
             where N: Into<String>, F: 'static + Fn(&str)->bool {
-        self.0.quiet_terminal(&name.into(), pred);
-        ParserBuilder(self.0)
+        self.gb.quiet_terminal(name, pred);
+        self
+    }
+
+    // Build the Grammar for the provided EBNF spec
+    pub fn into_grammar(self, start: &str, grammar: &str)
+            -> Result<Grammar, EbnfError> {
+        self.parse_grammar_fields(grammar)?.0
+            .into_grammar(start)
+            .map_err(EbnfError::Grammar)
     }
 
     // Build a parser for the provided grammar in EBNF syntax
     pub fn into_parser(self, start: &str, grammar: &str)
-            -> Result<EarleyParser, String> {
-        let user_grammar =
-            ParserBuilder::parse_grammar(self.0, grammar)?
-                .into_grammar(start)?;
-        Ok(EarleyParser::new(user_grammar))
+            -> Result<EarleyParser, EbnfError> {
+        Ok(EarleyParser::new(self.into_grammar(start, grammar)?))
     }
 }
@@ -1,6 +1,7 @@
 #![deny(warnings)]
 
 use crate::ebnf::{ebnf_grammar, ParserBuilder};
+use crate::treeficator::Tree;
 use std::fmt;
 
 #[test]
@@ -25,7 +26,7 @@ fn check_trees<T: fmt::Debug>(trees: &Vec<T>, expected: Vec<&str>) {
 #[test]
 fn minimal_parser() {
     let g = r#" Number := "0" ; "#;
-    let parser = ParserBuilder::default().treeficator(g, "Number");
+    let parser = ParserBuilder::default().treeficator(g, "Number").unwrap();
     let trees = parser(["0"].iter()).unwrap();
     check_trees(&trees, vec![r#"Node("Number -> 0", [Leaf("0", "0")])"#]);
 }
@@ -38,7 +39,7 @@ fn arith_parser() {
 
         Number := "0" | "1" | "2" | "3" ;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "expr");
+    let parser = ParserBuilder::default().treeficator(&g, "expr").unwrap();
     let trees = parser("3 + 2 + 1".split_whitespace()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -59,7 +60,7 @@ fn repetition() {
         arg := b { "," b } ;
         b := "0" | "1" ;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "arg");
+    let parser = ParserBuilder::default().treeficator(&g, "arg").unwrap();
     let trees = parser("1 , 0 , 1".split_whitespace()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -75,13 +76,35 @@ fn repetition() {
     ]);
 }
 
+#[test]
+fn splice_helpers() {
+    let g = r#"
+        arg := b { "," b } [ "!" ] ;
+        b := "0" | "1" | ( "x" "y" ) @xy ;
+    "#;
+    let parser = ParserBuilder::default().treeficator(&g, "arg").unwrap();
+    let trees = parser("1 , x y , 1".split_whitespace()).unwrap();
+    let trees = trees.into_iter().map(|t| t.splice_helpers()).collect();
+    // tagged helpers are kept
+    check_trees(&trees, vec![
+        concat!(
+            r#"Node("arg -> b <Uniq-3> <Uniq-5>", ["#,
+                r#"Node("b -> 1", [Leaf("1", "1")]), "#,
+                r#"Leaf(",", ","), "#,
+                r#"Node("b -> @xy", [Node("@xy -> x y", ["#,
+                    r#"Leaf("x", "x"), Leaf("y", "y")])]), "#,
+                r#"Leaf(",", ","), "#,
+                r#"Node("b -> 1", [Leaf("1", "1")])])"#)
+    ]);
+}
+
 #[test]
 fn repetition_tagged() {
     let g = r#"
         arg := b { "," b } @x;
         b := "0" | "1" ;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "arg");
+    let parser = ParserBuilder::default().treeficator(&g, "arg").unwrap();
     let trees = parser("1 , 0 , 1".split_whitespace()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -103,7 +126,7 @@ fn option() {
         complex := d [ "i" ];
         d := "0" | "1" | "2";
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "complex");
+    let parser = ParserBuilder::default().treeficator(&g, "complex").unwrap();
     let trees = parser(["1"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -129,7 +152,7 @@ fn option_tagged() {
         complex := d [ "i" ] @x;
         d := "0" | "1" | "2";
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "complex");
+    let parser = ParserBuilder::default().treeficator(&g, "complex").unwrap();
     let trees = parser(["1"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -154,7 +177,7 @@ fn grouping() {
     let g = r#"
         row := ("a" | "b") ("0" | "1") ;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "row");
+    let parser = ParserBuilder::default().treeficator(&g, "row").unwrap();
     let trees = parser(["b", "1"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -180,7 +203,7 @@ fn grouping_tagged() {
     let g = r#"
         row := ("a" | "b") @x ("0" | "1") @y;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "row");
+    let parser = ParserBuilder::default().treeficator(&g, "row").unwrap();
     let trees = parser(["b", "1"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -206,7 +229,7 @@ fn mixed() {
     let g = r#"
         row := "a" [ "b" ] ("0" | "1") [ "c" ];
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "row");
+    let parser = ParserBuilder::default().treeficator(&g, "row").unwrap();
     let trees = parser(["a", "0"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -248,7 +271,7 @@ fn mixed_tagged() {
     let g = r#"
         row := "a" [ "b" ]@x ("0" | "1")@y [ "c" ]@z;
     "#;
-    let parser = ParserBuilder::default().treeficator(&g, "row");
+    let parser = ParserBuilder::default().treeficator(&g, "row").unwrap();
     let trees = parser(["a", "0"].iter()).unwrap();
     check_trees(&trees, vec![
         concat!(
@@ -294,7 +317,7 @@ fn plug_terminal() {
     "#;
     let parser = ParserBuilder::default()
         .plug_terminal("Number", |i| i8::from_str(i).is_ok())
-        .treeficator(&g, "expr");
+        .treeficator(&g, "expr").unwrap();
 
     let trees = parser(["3", "+", "1"].iter()).unwrap();
     check_trees(&trees, vec![
@@ -305,3 +328,189 @@ fn plug_terminal() {
                 r#"Leaf("Number", "1")])"#)
     ]);
 }
+
+#[test]
+fn pattern_terminals() {
+    let g = r#"
+        assign := [a-z] '=' /[0-9]+(\.[0-9]+)?/ [ '!' ] ;
+    "#;
+    let parser = ParserBuilder::default().treeficator(&g, "assign").unwrap();
+    let trees = parser(["x", "=", "3.14"].iter()).unwrap();
+    check_trees(&trees, vec![
+        concat!(
+            r#"Node("assign -> [a-z] = /[0-9]+(\\.[0-9]+)?/ <Uniq-5>", ["#,
+                r#"Leaf("[a-z]", "x"), "#,
+                r#"Leaf("=", "="), "#,
+                r#"Leaf("/[0-9]+(\\.[0-9]+)?/", "3.14"), "#,
+                r#"Node("<Uniq-5> -> ", [])])"#)
+    ]);
+    assert!(parser(["x", "=", "3."].iter()).is_err());
+    assert!(parser(["xy", "=", "3"].iter()).is_err());
+    assert!(parser(["x", "=", "3", "!"].iter()).is_ok());
+}
+
+#[test]
+fn pattern_errors() {
+    use crate::ebnf::EbnfError;
+    let g = "num := /[0-9]+/ ;
+bad := /(a|b/ ;";
+    let error = ParserBuilder::parse_grammar(Default::default(), g).err();
+    assert_eq!(error, Some(EbnfError::Pattern{
+        line: 2, column: 8, pattern: "/(a|b/".to_string(),
+        error: "Regex error at 4: expected ')'".to_string(),
+    }));
+}
+
+#[test]
+fn syntax_errors() {
+    use crate::ebnf::EbnfError;
+    fn syntax_error(g: &str) -> EbnfError {
+        ParserBuilder::parse_grammar(Default::default(), g).err().unwrap()
+    }
+    let error = syntax_error("expr := expr '+' num\n     | num ;\nnum := ; ;");
+    assert_eq!(error, EbnfError::Syntax{
+        line: 3, column: 8, found: Some(";".to_string()),
+        expected: vec!["\"", "'", "(", "<Class>", "<Id>", "<Regex>", "@<Tag>", "[", "{"]
+            .into_iter().map(|e| e.to_string()).collect(),
+    });
+    assert_eq!(error.to_string(), concat!(
+        r#"Syntax error at 3:8: unexpected ";", "#,
+        r#"expected one of: " ' ( <Class> <Id> <Regex> @<Tag> [ {"#));
+
+    assert_eq!(syntax_error("expr = num ;"), EbnfError::Syntax{
+        line: 1, column: 6, found: Some("=".to_string()),
+        expected: vec![":=".to_string()],
+    });
+
+    assert_eq!(syntax_error("expr := num"), EbnfError::Syntax{
+        line: 1, column: 12, found: None,
+        expected: vec!["\"", "'", "(", "*", "+", ":", ";", "<Class>", "<Id>", "<Regex>",
+                   "?", "[", "{", "|"]
+            .into_iter().map(|e| e.to_string()).collect(),
+    });
+
+    // unterminated string
+    let error = syntax_error("expr := 'num ;");
+    assert_eq!(error, EbnfError::Token{
+        line: 1, column: 9, error: "unterminated string".to_string(),
+    });
+    assert_eq!(error.to_string(), "Syntax error at 1:9: unterminated string");
+}
+
+#[test]
+fn grammar_errors() {
+    use crate::ebnf::EbnfError;
+    let g = r#" Number := "0" ; "#;
+    let error = ParserBuilder::default().treeficator::<std::slice::Iter<&str>>(g, "Digit");
+    assert_eq!(error.err(), Some(EbnfError::Grammar("Missing Symbol: Digit".to_string())));
+    let error = ParserBuilder::default().into_parser("Digit", g);
+    assert_eq!(error.err(), Some(EbnfError::Grammar("Missing Symbol: Digit".to_string())));
+}
+
+#[test]
+fn postfix_operators() {
+    let g = r#"
+        list := "[" item* "]" ;
+        item := d+ ","? ;
+        d := "0" | "1" ;
+    "#;
+    let parser = ParserBuilder::default().treeficator(&g, "list").unwrap();
+    assert!(parser(["[", "]"].iter()).is_ok());
+    assert!(parser(["[", "0", "1", ",", "1", "]"].iter()).is_ok());
+    assert!(parser(["[", ",", "]"].iter()).is_err());
+    assert!(parser(["[", "0", ",", ",", "]"].iter()).is_err());
+    // postfix operators can be stacked and applied to groups
+    let g = r#" s := ("a" "b")+? ; "#;
+    let parser = ParserBuilder::default().treeficator(&g, "s").unwrap();
+    assert!(parser([].iter()).is_ok());
+    assert!(parser(["a", "b", "a", "b"].iter()).is_ok());
+    assert!(parser(["a", "b", "a"].iter()).is_err());
+}
+
+#[test]
+fn counted_repetition() {
+    let g = r#"
+        year := d{4} ;
+        hour := d{1,2} ;
+        many := d{2,} ;
+        d := "0" | "1" ;
+    "#;
+    for (start, good, bad) in [
+        ("year", vec!["1001"], vec!["100", "10011"]),
+        ("hour", vec!["1", "01"], vec!["", "011"]),
+        ("many", vec!["01", "0110"], vec!["1"]),
+    ] {
+        let parser = ParserBuilder::default().treeficator(&g, start).unwrap();
+        let chars = |input: &str| input.chars()
+            .map(|c| c.to_string()).collect::<Vec<_>>();
+        for input in good {
+            assert!(parser(chars(input).into_iter()).is_ok());
+        }
+        for input in bad {
+            assert!(parser(chars(input).into_iter()).is_err());
+        }
+    }
+    use crate::ebnf::EbnfError;
+    let error = ParserBuilder::default().into_parser("n", r#" n := "0"{3,1} ; "#);
+    assert_eq!(error.err(), Some(EbnfError::Grammar(
+        "Bad repetition bounds for 0".to_string())));
+}
+
+#[test]
+fn named_fields() {
+    let g = r#"
+        sum := lhs:sum op:("+" | "-") rhs:d | d ;
+        d := "0" | "1" | v:{ "x" } "!" ;
+    "#;
+    let parser = ParserBuilder::default().treeficator(&g, "sum").unwrap();
+    let trees = parser("1 - 0 + x x !".split_whitespace()).unwrap();
+    assert_eq!(trees.len(), 1);
+    let sum = &trees[0];
+    assert_eq!(sum.field("op"), Some(&Tree::Node(
+        "<Uniq-5> -> +".to_string(), vec![Tree::Leaf("+".to_string(), "+".to_string())])));
+    let lhs = sum.field("lhs").expect("missing lhs");
+    assert_eq!(lhs.field("lhs"), Some(&Tree::Node(
+        "sum -> d".to_string(), vec![Tree::Node(
+            "d -> 1".to_string(), vec![Tree::Leaf("1".to_string(), "1".to_string())])])));
+    assert!(lhs.field("nope").is_none());
+    // fields survive splicing helper nodes
+    let rhs = sum.clone().splice_helpers().field("rhs").cloned().unwrap();
+    let v = rhs.field("v").expect("missing v");
+    assert_eq!(v, &Tree::Node("<Uniq-12> -> x <Uniq-12>".to_string(), vec![
+        Tree::Leaf("x".to_string(), "x".to_string()),
+        Tree::Leaf("x".to_string(), "x".to_string())]));
+}
+
+#[test]
+fn undefined_nonterminals() {
+    use crate::ebnf::EbnfError;
+    let g = "expr := expr '+' term | term ;\nterm := nmu | '(' expr ')' | nmu ;";
+    let builder = || ParserBuilder::default()
+        .plug_terminal("num", |n| n.chars().all(|c| c.is_ascii_digit()));
+    let (_, warnings) = ParserBuilder::parse_grammar(
+        builder().into_grammar_builder(), g).unwrap();
+    assert_eq!(warnings, vec![EbnfError::Undefined{
+        line: 2, column: 9, name: "nmu".to_string(), include: None}]);
+    assert_eq!(warnings[0].to_string(), "Undefined nonterminal nmu at 2:9");
+    // only a warning, the grammar still builds
+    assert!(builder().into_parser("expr", g).is_ok());
+    // used by an included spec and the including one, reported once
+    let warnings = ParserBuilder::default()
+        .include_source("lib", "list := item | list ',' item ;")
+        .into_parts("@include \"lib\"\nitems := '[' list ']' | item ;", "items")
+        .unwrap().warnings;
+    assert_eq!(warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(), vec![
+        "Undefined nonterminal item in lib at 1:9"]);
+    // or defined by the spec including it
+    let warnings = ParserBuilder::default()
+        .include_source("lib", "list := item | list ',' item ;")
+        .into_parts("@include \"lib\"\nitems := '[' list ']' ;\nitem := 'x' ;", "items")
+        .unwrap().warnings;
+    assert_eq!(warnings, Vec::new());
+    // plugged terminals, literals and field labels are fine
+    let g = "expr := lhs:expr 'term' term | term ;\nterm := nmu ;";
+    let gb = ParserBuilder::default()
+        .plug_terminal("nmu", |n| n.chars().all(|c| c.is_ascii_digit()))
+        .into_grammar_builder();
+    assert_eq!(ParserBuilder::parse_grammar(gb, g).unwrap().1, Vec::new());
+}
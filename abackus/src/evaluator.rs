@@ -0,0 +1,138 @@
+#![deny(warnings)]
+
+// Semantic actions bound to the @tag of rule alternatives, so editing the
+// symbols of an alternative doesn't silently unbind its action.
+//
+//   expr := @add expr '+' term | @sub expr '-' term | term ;
+
+use crate::ebnf::{EbnfError, ParserBuilder, Tags};
+use earlgrey::{EarleyForest, EarleyParser, Grammar};
+use std::rc::Rc;
+
+type Action<'a, T> = Rc<dyn Fn(Vec<T>) -> T + 'a>;
+type Fallback<'a, T> = Rc<dyn Fn(&str, Vec<T>) -> T + 'a>;
+type Terminal<'a, T> = Box<dyn Fn(&str, &str) -> T + 'a>;
+
+/// Collects actions for the tags of a grammar, `build` checks them
+pub struct Evaluator<'a, T> {
+    grammar: Grammar,
+    tags: Tags,
+    terminal: Terminal<'a, T>,
+    actions: Vec<(String, Action<'a, T>)>,
+    fallback: Option<Fallback<'a, T>>,
+}
+
+impl ParserBuilder {
+    /// Evaluator for `grammar` whose leaves are built by `terminal`
+    pub fn evaluator<'a, T, F>(self, grammar: &str, start: &str, terminal: F)
+            -> Result<Evaluator<'a, T>, EbnfError>
+            where F: Fn(&str, &str) -> T + 'a {
+        let parsed = self.parse_included(grammar)?;
+        let grammar = parsed.gb.into_grammar(start).map_err(EbnfError::Grammar)?;
+        Ok(Evaluator{
+            grammar,
+            tags: parsed.tags,
+            terminal: Box::new(terminal),
+            actions: Vec::new(),
+            fallback: None,
+        })
+    }
+}
+
+impl<'a, T: Clone + 'a> Evaluator<'a, T> {
+    /// Action for the alternatives tagged `@tag`
+    pub fn action<F>(mut self, tag: &str, action: F) -> Self
+            where F: Fn(Vec<T>) -> T + 'a {
+        let tag = tag.strip_prefix('@').unwrap_or(tag);
+        self.actions.push((tag.to_string(), Rc::new(action)));
+        self
+    }
+
+    /// Action for rules without a tagged action, it gets the rule string.
+    /// Without one those rules pass their only child up.
+    pub fn fallback<F>(mut self, action: F) -> Self
+            where F: Fn(&str, Vec<T>) -> T + 'a {
+        self.fallback = Some(Rc::new(action));
+        self
+    }
+
+    /// Parser and forest running the actions. Fails if an action was
+    /// given for a tag that no alternative has.
+    pub fn build(self) -> Result<(EarleyParser, EarleyForest<'a, T>), EbnfError> {
+        let Evaluator{grammar, tags, terminal, actions, fallback} = self;
+        for (tag, _) in &actions {
+            if !tags.values().any(|t| t == tag) {
+                return Err(EbnfError::Grammar(format!("Unknown tag @{}", tag)));
+            }
+        }
+        let mut forest = EarleyForest::new(terminal);
+        for rule in &grammar.rules {
+            let rule_str = rule.to_string();
+            // later actions for a tag replace earlier ones
+            let action = tags.get(&rule_str)
+                .and_then(|tag| actions.iter().rev().find(|a| &a.0 == tag));
+            if let Some((_, action)) = action {
+                let action = action.clone();
+                forest.action(&rule_str, move |args| action(args));
+            } else if let Some(fallback) = &fallback {
+                let fallback = fallback.clone();
+                let name = rule_str.clone();
+                forest.action(&rule_str, move |args| fallback(&name, args));
+            } else if rule.spec.len() == 1 {
+                forest.action(&rule_str, |mut args| args.swap_remove(0));
+            }
+        }
+        Ok((EarleyParser::new(grammar), forest))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::{EbnfError, ParserBuilder};
+    use lexers::DelimTokenizer;
+
+    #[test]
+    fn tagged_actions() {
+        let g = r#"
+            expr := @add expr '+' term | @sub expr '-' term | term ;
+            term := @neg '-' num | num | @group '(' expr ')' ;
+        "#;
+        let (parser, forest) = ParserBuilder::default()
+            .plug_terminal("num", |n| n.parse::<f64>().is_ok())
+            .evaluator(g, "expr", |_, lexeme| lexeme.parse().unwrap_or(0.0))
+            .unwrap()
+            .action("add", |n| n[0] + n[2])
+            .action("@sub", |n| n[0] - n[2])
+            .action("neg", |n| -n[1])
+            .build().unwrap();
+        let eval = |input: &str| forest.eval(&parser.parse(
+            DelimTokenizer::new(input.chars(), " ", true)).unwrap());
+        assert_eq!(eval("1 + 2 - - 4"), Ok(7.0));
+        // @group has no action and more than one child
+        assert_eq!(eval("1 + ( 2 )"),
+                   Err("Missing Action: term -> ( expr )".to_string()));
+    }
+
+    #[test]
+    fn fallback_and_unknown_tags() {
+        let g = "list := @pair item item | item ; item := 'a' | 'b' ;";
+        let (parser, forest) = ParserBuilder::default()
+            .evaluator(g, "list", |_, lexeme| lexeme.to_string())
+            .unwrap()
+            .action("pair", |n| format!("{}{}", n[0], n[1]))
+            .fallback(|rule, n| format!("<{}:{}>", rule, n.join("")))
+            .build().unwrap();
+        let tokens = DelimTokenizer::new("a b".chars(), " ", true);
+        assert_eq!(forest.eval(&parser.parse(tokens).unwrap()),
+                   Ok("<item -> a:a><item -> b:b>".to_string()));
+        let error = ParserBuilder::default()
+            .evaluator(g, "list", |_, lexeme| lexeme.to_string())
+            .unwrap()
+            .action("pair", |n| n.join(""))
+            .action("triple", |n| n.join(""))
+            .build().err();
+        assert_eq!(error, Some(EbnfError::Grammar("Unknown tag @triple".to_string())));
+    }
+}
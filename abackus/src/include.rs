@@ -0,0 +1,232 @@
+#![deny(warnings)]
+
+// @include "name" lines pull the rules of other specs in before the rules
+// of the spec including them. Names are looked up in sources registered on
+// the ParserBuilder, then the bundled "std" library, then as files next to
+// the including file, in include dirs and relative to the working dir.
+
+use crate::ebnf::{EbnfError, Fields, ParserBuilder, Tags, Tokens};
+use earlgrey::GrammarBuilder;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Rules for numbers, identifiers, quoted strings and lists of them,
+/// available as `@include "std"`
+pub const STD: &str = include_str!("std.ebnf");
+
+// Where ParserBuilder looks for included specs
+#[derive(Default)]
+pub(crate) struct Includes {
+    dirs: Vec<PathBuf>,
+    sources: HashMap<String, String>,
+}
+
+// A spec that was parsed, with @include lines blanked, its tokens, file and
+// the name it was included by (None for the including spec)
+pub(crate) struct Included {
+    pub spec: String,
+    pub tokens: Tokens,
+    pub path: Option<PathBuf>,
+    pub include: Option<String>,
+}
+
+// Grammar parsed from a spec and its includes, with field labels and tags
+// of its rules, warnings about them and all the specs parsed, the
+// including one last
+pub(crate) struct Parsed {
+    pub gb: GrammarBuilder,
+    pub fields: Fields,
+    pub tags: Tags,
+    pub warnings: Vec<EbnfError>,
+    pub specs: Vec<Included>,
+}
+
+// Included specs being parsed (to find cycles) or already parsed
+#[derive(Default)]
+struct Visited {
+    stack: Vec<String>,
+    done: HashSet<String>,
+    // an include closed a cycle, it's reported once from the outermost spec
+    cycle: bool,
+}
+
+// Included names with the line and column of their @include
+type Directives = Vec<(String, usize, usize)>;
+
+// Blank @include lines keeping line numbers, collect the included names
+fn directives(spec: &str) -> Result<(String, Directives), EbnfError> {
+    let mut lines = Vec::new();
+    let mut names = Vec::new();
+    for (idx, line) in spec.split('\n').enumerate() {
+        let trimmed = line.trim_start();
+        let rest = match trimmed.strip_prefix("@include") {
+            Some(rest) if !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') => rest,
+            _ => {
+                lines.push(line);
+                continue;
+            }
+        };
+        let indent = line.chars().count() - trimmed.chars().count();
+        let column = indent + 1;
+        let arg = rest.trim_start();
+        let arg_column = line.chars().count() - arg.chars().count() + 1;
+        // "name" or 'name' optionally followed by a comment
+        let quote = arg.chars().next().filter(|&q| q == '"' || q == '\'');
+        let name = quote.and_then(|q| {
+            let (name, after) = arg[1..].split_once(q)?;
+            let after = after.trim();
+            (!name.is_empty() && (after.is_empty() || after.starts_with('#')))
+                .then_some(name)
+        });
+        match name {
+            Some(name) => names.push((name.to_string(), idx + 1, column)),
+            None => return Err(EbnfError::Syntax{
+                line: idx + 1,
+                column: arg_column,
+                found: arg.split_whitespace().next().map(String::from),
+                expected: vec!["\"<name>\"".to_string()],
+            }),
+        }
+        lines.push("");
+    }
+    Ok((lines.join("\n"), names))
+}
+
+impl Includes {
+    // Key, spec and file for an included name
+    fn resolve(&self, name: &str, dir: Option<&Path>)
+            -> Result<(String, String, Option<PathBuf>), String> {
+        if let Some(spec) = self.sources.get(name) {
+            return Ok((name.to_string(), spec.clone(), None));
+        }
+        if name == "std" {
+            return Ok((name.to_string(), STD.to_string(), None));
+        }
+        let path = dir.iter().map(|d| d.join(name))
+            .chain(self.dirs.iter().map(|d| d.join(name)))
+            .chain(std::iter::once(PathBuf::from(name)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("Can't find {}", name))?;
+        let spec = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Can't read {}: {}", path.display(), e))?;
+        let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+        Ok((key.display().to_string(), spec, Some(path)))
+    }
+
+    // Parse spec after the specs it includes into parsed
+    fn parse(&self, parsed: &mut Parsed, visited: &mut Visited, spec: &str,
+             path: Option<PathBuf>, include: Option<String>) -> Result<(), EbnfError> {
+        let (spec, names) = directives(spec)?;
+        let dir = path.as_ref().and_then(|p| p.parent()).map(Path::to_path_buf);
+        for (name, line, column) in names {
+            let error = |error: String| EbnfError::Include{
+                line, column, name: name.clone(), error
+            };
+            let (key, included, included_path) =
+                self.resolve(&name, dir.as_deref()).map_err(error)?;
+            if visited.done.contains(&key) {
+                continue;
+            }
+            if visited.stack.contains(&key) {
+                let mut cycle = visited.stack.clone();
+                cycle.push(key);
+                visited.cycle = true;
+                return Err(error(format!("Cycle {}", cycle.join(" -> "))));
+            }
+            visited.stack.push(key.clone());
+            self.parse(parsed, visited, &included, included_path, Some(name.clone()))
+                .map_err(|e| match e {
+                    EbnfError::Include{error: cycle, ..} if visited.cycle => error(cycle),
+                    e => error(e.to_string()),
+                })?;
+            visited.stack.pop();
+            visited.done.insert(key);
+        }
+        let tokens = ParserBuilder::parse_spec(parsed, &spec)?;
+        parsed.specs.push(Included{spec, tokens, path, include});
+        Ok(())
+    }
+}
+
+impl ParserBuilder {
+    /// Also look for `@include` files in `dir`
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.includes.dirs.push(dir.into());
+        self
+    }
+
+    /// Make `spec` available as `@include "name"`
+    pub fn include_source<N, S>(mut self, name: N, spec: S) -> Self
+            where N: Into<String>, S: Into<String> {
+        self.includes.sources.insert(name.into(), spec.into());
+        self
+    }
+
+    // Parse a grammar and the specs it includes, returning all of them
+    pub(crate) fn parse_included(self, spec: &str) -> Result<Parsed, EbnfError> {
+        let mut parsed = Parsed{
+            gb: self.gb, fields: Fields::new(), tags: Tags::new(),
+            warnings: Vec::new(), specs: Vec::new()
+        };
+        self.includes.parse(&mut parsed, &mut Visited::default(), spec, None, None)?;
+        // names may be defined by any spec, including the ones after their use
+        parsed.warnings = ParserBuilder::check_references(&parsed.gb, &parsed.specs);
+        Ok(parsed)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::{EbnfError, ParserBuilder};
+    use lexers::DelimTokenizer;
+
+    #[test]
+    fn std_library() {
+        let g = r#"
+            @include "std"
+            call := ident '(' [args] ')' ;
+            args := arg | args ',' arg ;
+            arg := num | string | call ;
+        "#;
+        let parser = ParserBuilder::default()
+            .text_sexprificator(g, "call").unwrap();
+        let trees = parser(r#"f(1, 2.5e3, "a \" b", g('x'))"#).unwrap();
+        assert_eq!(trees.len(), 1);
+        let g = "@include 'std' # lists\nlist := num_list ;";
+        let parser = ParserBuilder::default().sexprificator(g, "list").unwrap();
+        assert!(parser(DelimTokenizer::new("1 2.5 .5".chars(), " ", true)).is_ok());
+        assert!(parser(DelimTokenizer::new("1 x".chars(), " ", true)).is_err());
+    }
+
+    #[test]
+    fn include_sources() {
+        let parser = ParserBuilder::default()
+            .include_source("atoms", "@include \"std\"\natom := num | ident ;")
+            .include_source("lists", "@include \"atoms\"\nlist := '(' atom* ')' ;")
+            .text_sexprificator("@include \"lists\"\n@include \"atoms\"\nexpr := atom | list ;", "expr")
+            .unwrap();
+        assert_eq!(parser("(a 1 b)").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn include_errors() {
+        let parse = |builder: ParserBuilder, g: &str|
+            builder.into_parser("x", g).err().unwrap();
+        assert_eq!(parse(ParserBuilder::default(), "x := y ;\n  @include \"nope.ebnf\""),
+                   EbnfError::Include{line: 2, column: 3, name: "nope.ebnf".to_string(),
+                                      error: "Can't find nope.ebnf".to_string()});
+        let cycle = ParserBuilder::default()
+            .include_source("a", "@include \"b\"\na := 'a' ;")
+            .include_source("b", "@include \"a\"\nb := 'b' ;");
+        assert_eq!(parse(cycle, "@include \"a\"\nx := a ;").to_string(),
+                   "Include a at 1:1: Cycle a -> b -> a");
+        let broken = ParserBuilder::default().include_source("y", "\ny := ;");
+        assert_eq!(parse(broken, "@include \"y\"\nx := y ;").to_string(),
+                   "Include y at 1:1: Syntax error at 2:6: unexpected \";\", \
+                    expected one of: \" ' ( <Class> <Id> <Regex> @<Tag> [ {");
+        assert!(matches!(parse(ParserBuilder::default(), "@include std\nx := 'x' ;"),
+                         EbnfError::Syntax{line: 1, column: 10, ..}));
+    }
+}
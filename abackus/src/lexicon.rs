@@ -0,0 +1,259 @@
+#![deny(warnings)]
+
+// Tokenizer derived from a grammar spec: quoted literals, /regex/ and
+// [class] terminals and any terminals plugged into the ParserBuilder.
+
+use crate::ebnf::{EbnfError, Fields, ParserBuilder};
+use crate::treeficator::{Sexpr, Tree};
+use earlgrey::{EarleyForest, EarleyParser, Grammar};
+use lexers::{EbnfTokenizer, Regex};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+type Predicate = Box<dyn Fn(&str) -> bool>;
+
+/// Longest-match tokenizer for the terminals of a grammar. Whitespace
+/// separates tokens and is otherwise skipped. When matches tie, literals
+/// win over patterns so keywords aren't taken as identifiers.
+pub struct GrammarTokenizer {
+    literals: Vec<String>,
+    patterns: Vec<Regex>,
+    // plugged terminals can only be tried on each candidate prefix
+    plugged: Vec<Predicate>,
+}
+
+// Quoted literals and /regex/ or [class] patterns used in a spec
+pub(crate) fn spec_terminals(spec: &str) -> (HashSet<String>, HashSet<String>) {
+    let tokens: Vec<_> = EbnfTokenizer::new(spec.chars()).collect();
+    let mut literals = HashSet::new();
+    let mut patterns = HashSet::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        let quoted = (token == "'" || token == "\"") &&
+            tokens.get(idx + 2) == Some(token);
+        if quoted {
+            // an empty literal can't be matched without consuming input
+            if !tokens[idx + 1].is_empty() {
+                literals.insert(tokens[idx + 1].clone());
+            }
+            idx += 3;
+            continue;
+        }
+        if token.len() > 2 && (token.starts_with('/') && token.ends_with('/') ||
+                               token.starts_with('[') && token.ends_with(']')) {
+            patterns.insert(token.clone());
+        }
+        idx += 1;
+    }
+    (literals, patterns)
+}
+
+// Regex for a /regex/ or [class] terminal
+pub(crate) fn pattern_regex(pattern: &str) -> Result<Regex, String> {
+    match pattern.strip_prefix('/') {
+        Some(p) => Regex::new(&p[..p.len() - 1]),
+        None => Regex::new(pattern),
+    }
+}
+
+impl GrammarTokenizer {
+    /// Collect the terminals of `grammar`, built from the EBNF `spec`
+    pub fn new(grammar: &Grammar, spec: &str) -> Result<GrammarTokenizer, String> {
+        let (literals, patterns) = spec_terminals(spec);
+        let mut plugged = Vec::new();
+        let mut seen = HashSet::new();
+        for symbol in grammar.rules.iter().flat_map(|r| r.spec.iter()) {
+            let name = match symbol.terminal() {
+                Some((name, _)) => name,
+                None => continue,
+            };
+            if literals.contains(name) || patterns.contains(name) ||
+                    !seen.insert(name.to_string()) {
+                continue;
+            }
+            let symbol = symbol.clone();
+            plugged.push(Box::new(move |s: &str|
+                symbol.terminal().is_some_and(|(_, pred)| pred(s)))
+                as Predicate);
+        }
+        let mut patterns: Vec<_> = patterns.into_iter().collect();
+        patterns.sort();
+        let patterns = patterns.iter()
+            .map(|p| pattern_regex(p))
+            .collect::<Result<_, _>>()?;
+        let mut literals: Vec<_> = literals.into_iter().collect();
+        literals.sort();
+        Ok(GrammarTokenizer{literals, patterns, plugged})
+    }
+
+    // Length of the longest token at the start of input
+    fn match_len(&self, input: &str) -> Option<usize> {
+        let literal = self.literals.iter()
+            .filter(|l| input.starts_with(l.as_str()))
+            .map(|l| l.len())
+            .max()
+            .filter(|&len| len > 0);
+        let pattern = self.patterns.iter()
+            .filter_map(|p| p.match_len(input))
+            .max();
+        // plugged terminals are tried up to the next whitespace
+        let word = input.find(char::is_whitespace).unwrap_or(input.len());
+        let plugged = input[..word].char_indices().rev()
+            .map(|(idx, c)| idx + c.len_utf8())
+            .find(|&end| self.plugged.iter().any(|pred| pred(&input[..end])));
+        let longest = pattern.max(plugged).filter(|&len| len > 0);
+        match (literal, longest) {
+            (Some(l), Some(p)) if p > l => Some(p),
+            (Some(l), _) => Some(l),
+            (None, p) => p,
+        }
+    }
+
+    /// Split input into tokens
+    pub fn tokenize(&self, input: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            let len = match self.match_len(rest) {
+                Some(len) => len,
+                None => {
+                    let offset = input.len() - rest.len();
+                    let line = input[..offset].matches('\n').count() + 1;
+                    let column = input[..offset].rsplit('\n').next()
+                        .map_or(0, |l| l.chars().count()) + 1;
+                    let found: String = rest.chars()
+                        .take_while(|c| !c.is_whitespace()).take(10).collect();
+                    return Err(format!("No token matches at {}:{}: {:?}",
+                                       line, column, found));
+                }
+            };
+            tokens.push(rest[..len].to_string());
+            rest = rest[len..].trim_start();
+        }
+        Ok(tokens)
+    }
+}
+
+/// A grammar with a tokenizer for its terminals, the warnings about its
+/// spec and the files it `@include`s, for tools that use them separately
+/// from a single parse of the spec
+pub struct GrammarParts {
+    pub grammar: Grammar,
+    pub tokenizer: GrammarTokenizer,
+    pub warnings: Vec<EbnfError>,
+    pub included: Vec<PathBuf>,
+    fields: Fields,
+}
+
+impl GrammarParts {
+    /// Evaler building the same trees as a treeficator
+    pub fn tree_builder(&self) -> EarleyForest<'static, Tree> {
+        ParserBuilder::tree_builder(&self.grammar, self.fields.clone())
+    }
+}
+
+impl ParserBuilder {
+    // Build the grammar, a tokenizer for its terminals, its warnings and
+    // the files it includes
+    pub fn into_parts(self, grammar: &str, start: &str)
+            -> Result<GrammarParts, EbnfError> {
+        let parsed = self.parse_included(grammar)?;
+        let user = parsed.gb.into_grammar(start).map_err(EbnfError::Grammar)?;
+        // terminals may come from included specs too
+        let spec: Vec<_> = parsed.specs.iter().map(|s| s.spec.as_str()).collect();
+        let tokenizer = GrammarTokenizer::new(&user, &spec.join("\n"))
+            .map_err(EbnfError::Grammar)?;
+        let included = parsed.specs.into_iter().filter_map(|s| s.path).collect();
+        Ok(GrammarParts{
+            grammar: user, tokenizer, warnings: parsed.warnings, included,
+            fields: parsed.fields
+        })
+    }
+
+    // Tokenizer for the literals and terminals of a grammar
+    pub fn tokenizer(self, grammar: &str, start: &str)
+            -> Result<GrammarTokenizer, EbnfError> {
+        Ok(self.into_parts(grammar, start)?.tokenizer)
+    }
+
+    // Like treeficator but parses text with a tokenizer derived from grammar
+    pub fn text_treeficator(self, grammar: &str, start: &str)
+        -> Result<impl Fn(&str) -> Result<Vec<Tree>, String>, EbnfError>
+    {
+        let parts = self.into_parts(grammar, start)?;
+        let tree_builder = ParserBuilder::tree_builder(&parts.grammar, parts.fields);
+        let tokenizer = parts.tokenizer;
+        let parser = EarleyParser::new(parts.grammar);
+        Ok(move |input: &str| {
+            let tokens = tokenizer.tokenize(input)?;
+            tree_builder.eval_all(&parser.parse(tokens.iter())?)
+        })
+    }
+
+    // Like sexprificator but parses text with a tokenizer derived from grammar
+    pub fn text_sexprificator(self, grammar: &str, start: &str)
+        -> Result<impl Fn(&str) -> Result<Vec<Sexpr>, String>, EbnfError>
+    {
+        let parts = self.into_parts(grammar, start)?;
+        let tree_builder = ParserBuilder::sexpr_builder(&parts.grammar);
+        let tokenizer = parts.tokenizer;
+        let parser = EarleyParser::new(parts.grammar);
+        Ok(move |input: &str| {
+            let tokens = tokenizer.tokenize(input)?;
+            tree_builder.eval_all(&parser.parse(tokens.iter())?)
+        })
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::ParserBuilder;
+
+    #[test]
+    fn grammar_tokenizer() {
+        let g = r#"
+            stmt := 'if' expr 'then' stmt | id '=' expr | id '==' expr ;
+            expr := id | num | /"[^"]*"/ ;
+            id   := /[a-z_][a-z0-9_]*/ ;
+        "#;
+        let tokenizer = ParserBuilder::default()
+            .plug_terminal("num", |n| n.parse::<f64>().is_ok())
+            .tokenizer(g, "stmt").unwrap();
+        assert_eq!(tokenizer.tokenize("if x==1.5e3 then iffy = \"a b\"").unwrap(),
+                   vec!["if", "x", "==", "1.5e3", "then", "iffy", "=", "\"a b\""]);
+        assert_eq!(tokenizer.tokenize(" \n ").unwrap(), Vec::<String>::new());
+        assert_eq!(tokenizer.tokenize("x = 1\ny = ?"),
+                   Err("No token matches at 2:5: \"?\"".to_string()));
+    }
+
+    #[test]
+    fn text_parsers() {
+        let g = r#"
+            expr := expr [+-] term | term ;
+            term := num | '(' expr ')' ;
+        "#;
+        let parser = ParserBuilder::default()
+            .plug_terminal("num", |n| n.chars().all(|c| c.is_ascii_digit()))
+            .text_sexprificator(g, "expr").unwrap();
+        let trees = parser("1+(22 -3)").unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].to_string(), "(1 + (\"(\" (22 - 3) \")\"))");
+        let parser = ParserBuilder::default()
+            .plug_terminal("num", |n| n.chars().all(|c| c.is_ascii_digit()))
+            .text_treeficator(g, "expr").unwrap();
+        assert_eq!(parser("1+2").unwrap()[0].select("'num'").unwrap().len(), 2);
+        assert!(parser("1+").is_err());
+    }
+
+    #[test]
+    fn empty_literal() {
+        let g = "x := '' 'a' ;";
+        let tokenizer = ParserBuilder::default().tokenizer(g, "x").unwrap();
+        assert_eq!(tokenizer.tokenize("a a").unwrap(), vec!["a", "a"]);
+        assert_eq!(tokenizer.tokenize("b"),
+                   Err("No token matches at 1:1: \"b\"".to_string()));
+    }
+}
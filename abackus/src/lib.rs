@@ -2,8 +2,30 @@
 
 mod ebnf;
 mod treeficator;
-pub use crate::ebnf::ParserBuilder;
+pub use crate::ebnf::{EbnfError, ParserBuilder};
 pub use crate::treeficator::{Tree, Sexpr};
 
+mod serialize;
+
+mod dialect;
+pub use crate::dialect::Dialect;
+
+mod include;
+pub use crate::include::STD;
+
+mod evaluator;
+pub use crate::evaluator::Evaluator;
+
+mod lexicon;
+pub use crate::lexicon::{GrammarParts, GrammarTokenizer};
+
+mod query;
+pub use crate::query::{Selector, SexprFold, TreeFold, Visitor};
+
+pub mod codegen;
+
+pub mod typed;
+pub use crate::typed::Grammar;
+
 #[cfg(test)]
 mod ebnf_test;
@@ -0,0 +1,341 @@
+#![deny(warnings)]
+
+use crate::treeficator::{Sexpr, Tree};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Walk a Tree or Sexpr depth-first. `enter` is called before visiting
+/// children (return false to skip them) and `leave` after.
+pub trait Visitor<T> {
+    fn enter(&mut self, _node: &T) -> bool { true }
+    fn leave(&mut self, _node: &T) {}
+}
+
+/// Bottom-up fold of a Tree into some other value
+pub trait TreeFold {
+    type Output;
+    fn leaf(&mut self, symbol: &str, lexeme: &str) -> Self::Output;
+    fn node(&mut self, rule: &str, subn: Vec<Self::Output>) -> Self::Output;
+    /// Labeled children fold as their content unless overridden
+    fn field(&mut self, _name: &str, value: Self::Output) -> Self::Output {
+        value
+    }
+}
+
+/// Bottom-up fold of an Sexpr into some other value
+pub trait SexprFold {
+    type Output;
+    fn atom(&mut self, lexeme: &str) -> Self::Output;
+    fn list(&mut self, subn: Vec<Self::Output>) -> Self::Output;
+}
+
+impl Tree {
+    pub fn walk<V: Visitor<Tree>>(&self, visitor: &mut V) {
+        if visitor.enter(self) {
+            match self {
+                Tree::Node(_, subn) => subn.iter().for_each(|n| n.walk(visitor)),
+                Tree::Field(_, n) => n.walk(visitor),
+                Tree::Leaf(_, _) => (),
+            }
+        }
+        visitor.leave(self);
+    }
+
+    pub fn fold<F: TreeFold>(&self, folder: &mut F) -> F::Output {
+        match self {
+            Tree::Leaf(symbol, lexeme) => folder.leaf(symbol, lexeme),
+            Tree::Node(rule, subn) => {
+                let subn = subn.iter().map(|n| n.fold(folder)).collect();
+                folder.node(rule, subn)
+            },
+            Tree::Field(name, n) => {
+                let value = n.fold(folder);
+                folder.field(name, value)
+            },
+        }
+    }
+
+    // Nodes matching a selector, see Selector
+    pub fn select(&self, selector: &str) -> Result<Vec<&Tree>, String> {
+        Ok(Selector::new(selector)?.select(self))
+    }
+}
+
+impl Sexpr {
+    pub fn walk<V: Visitor<Sexpr>>(&self, visitor: &mut V) {
+        if visitor.enter(self) {
+            if let Sexpr::List(subn) = self {
+                subn.iter().for_each(|n| n.walk(visitor));
+            }
+        }
+        visitor.leave(self);
+    }
+
+    pub fn fold<F: SexprFold>(&self, folder: &mut F) -> F::Output {
+        match self {
+            Sexpr::Atom(lexeme) => folder.atom(lexeme),
+            Sexpr::List(subn) => {
+                let subn = subn.iter().map(|n| n.fold(folder)).collect();
+                folder.list(subn)
+            },
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Step {
+    Any,              // *
+    Node(String),     // factor: Nodes whose rule head is factor
+    Leaf(String),     // 'num': Leaves whose symbol is num
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Combinator { Descendant, Child }
+
+/// Small selector language to find nodes of a Tree (CSS style).
+///  - `factor` Nodes whose rule head is `factor`
+///  - `'num'` or `"+"` Leaves whose symbol is `num` or `+`
+///  - `*` any Node or Leaf
+///  - `group factor` factors that are descendants of a group
+///  - `group > factor` factors that are direct children of a group
+///  - `a, b` either selector
+///
+/// Field labels are transparent, they don't count as a level.
+#[derive(Clone,Debug)]
+pub struct Selector(Vec<Vec<(Combinator, Step)>>);
+
+impl Selector {
+    pub fn new(spec: &str) -> Result<Selector, String> {
+        let mut alternatives = Vec::new();
+        let mut chars = spec.chars().peekable();
+        loop {
+            let steps = Selector::parse_steps(&mut chars, spec)?;
+            if steps.is_empty() {
+                return Err(format!("Empty selector in {:?}", spec));
+            }
+            alternatives.push(steps);
+            // parse_steps stops at a ',' (outside quotes) or the end
+            if chars.next().is_none() {
+                return Ok(Selector(alternatives));
+            }
+        }
+    }
+
+    fn parse_steps(chars: &mut Peekable<Chars>, spec: &str)
+            -> Result<Vec<(Combinator, Step)>, String> {
+        let mut steps = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        while let Some(&c) = chars.peek() {
+            if c == ',' {
+                break;
+            }
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+            if c == '>' {
+                if steps.is_empty() || combinator == Combinator::Child {
+                    return Err(format!("Misplaced '>' in {:?}", spec));
+                }
+                chars.next();
+                combinator = Combinator::Child;
+                continue;
+            }
+            let step = match c {
+                '*' => { chars.next(); Step::Any },
+                '\'' | '"' => {
+                    chars.next();
+                    let mut symbol = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(n) if n == c => break,
+                            Some(n) => symbol.push(n),
+                            None => return Err(format!("Unterminated quote in {:?}", spec)),
+                        }
+                    }
+                    if symbol.is_empty() {
+                        return Err(format!("Bad quoted symbol in {:?}", spec));
+                    }
+                    Step::Leaf(symbol)
+                },
+                _ => {
+                    let mut head = String::new();
+                    while let Some(&n) = chars.peek() {
+                        if n.is_whitespace() || "*'\">,".contains(n) {
+                            break;
+                        }
+                        head.push(n);
+                        chars.next();
+                    }
+                    Step::Node(head)
+                },
+            };
+            steps.push((combinator, step));
+            combinator = Combinator::Descendant;
+        }
+        if combinator == Combinator::Child {
+            return Err(format!("Dangling '>' in {:?}", spec));
+        }
+        Ok(steps)
+    }
+
+    fn step_matches(step: &Step, tree: &Tree) -> bool {
+        match (step, tree) {
+            (Step::Any, _) => true,
+            (Step::Node(head), Tree::Node(rule, _)) =>
+                rule.split(" -> ").next() == Some(head.as_str()),
+            (Step::Leaf(symbol), Tree::Leaf(s, _)) => s == symbol,
+            _ => false,
+        }
+    }
+
+    // Match steps right to left against tree and its ancestors
+    fn matches(steps: &[(Combinator, Step)], tree: &Tree,
+               ancestors: &[&Tree]) -> bool {
+        let ((combinator, step), rest) = match steps.split_last() {
+            Some(last) => last,
+            None => return true,
+        };
+        if !Selector::step_matches(step, tree) {
+            return false;
+        }
+        if rest.is_empty() {
+            return true;
+        }
+        match combinator {
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, above)) => Selector::matches(rest, parent, above),
+                None => false,
+            },
+            Combinator::Descendant => (0..ancestors.len()).rev().any(|idx|
+                Selector::matches(rest, ancestors[idx], &ancestors[..idx])),
+        }
+    }
+
+    fn collect<'t>(&self, tree: &'t Tree, ancestors: &mut Vec<&'t Tree>,
+                   found: &mut Vec<&'t Tree>) {
+        if let Tree::Field(_, n) = tree {
+            return self.collect(n, ancestors, found);
+        }
+        if self.0.iter().any(|steps| Selector::matches(steps, tree, ancestors)) {
+            found.push(tree);
+        }
+        if let Tree::Node(_, subn) = tree {
+            ancestors.push(tree);
+            for n in subn {
+                self.collect(n, ancestors, found);
+            }
+            ancestors.pop();
+        }
+    }
+
+    // Matching nodes in depth-first order
+    pub fn select<'t>(&self, tree: &'t Tree) -> Vec<&'t Tree> {
+        let mut found = Vec::new();
+        self.collect(tree, &mut Vec::new(), &mut found);
+        found
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::ParserBuilder;
+    use crate::treeficator::{Sexpr, Tree};
+    use super::{SexprFold, TreeFold, Visitor};
+
+    fn tree() -> Tree {
+        let g = r#"
+            expr   := expr '+' factor | factor ;
+            factor := num | group ;
+            group  := '(' expr ')' ;
+        "#;
+        let parser = ParserBuilder::default()
+            .plug_terminal("num", |n| n.chars().all(|c| c.is_ascii_digit()))
+            .treeficator(g, "expr").unwrap();
+        let mut trees = parser("1 + ( 2 + 3 )".split_whitespace()).unwrap();
+        trees.remove(0)
+    }
+
+    fn lexemes(found: Vec<&Tree>) -> Vec<String> {
+        found.into_iter().map(|t| match t {
+            Tree::Leaf(_, lexeme) => lexeme.clone(),
+            Tree::Node(rule, _) => rule.clone(),
+            Tree::Field(name, _) => name.clone(),
+        }).collect()
+    }
+
+    #[test]
+    fn select_nodes() {
+        let tree = tree();
+        assert_eq!(tree.select("factor").unwrap().len(), 4);
+        assert_eq!(lexemes(tree.select("'num'").unwrap()), vec!["1", "2", "3"]);
+        assert_eq!(lexemes(tree.select("group 'num'").unwrap()), vec!["2", "3"]);
+        assert_eq!(lexemes(tree.select("group > '+'").unwrap()), Vec::<String>::new());
+        assert_eq!(lexemes(tree.select("group > expr > '+'").unwrap()), vec!["+"]);
+        assert_eq!(lexemes(tree.select("group>*").unwrap()), vec![
+            "(", "expr -> expr + factor", ")"]);
+        assert_eq!(lexemes(tree.select("group, '1'").unwrap()).len(), 1);
+        assert_eq!(lexemes(tree.select("group, 'num' , \"(\"").unwrap()), vec![
+            "1", "group -> ( expr )", "(", "2", "3"]);
+        for bad in &["", "a,", "> a", "a >", "a > > b", "''", "'num", "a, 'b"] {
+            assert!(tree.select(bad).is_err(), "{:?} should fail", bad);
+        }
+        assert_eq!(tree.select("'num").unwrap_err(), "Unterminated quote in \"'num\"");
+    }
+
+    #[test]
+    fn select_quoted_comma() {
+        let parser = ParserBuilder::default()
+            .plug_terminal("num", |n| n.chars().all(|c| c.is_ascii_digit()))
+            .treeficator("list := num | list ',' num ;", "list").unwrap();
+        let tree = parser("1 , 2".split_whitespace()).unwrap().remove(0);
+        assert_eq!(lexemes(tree.select("','").unwrap()), vec![","]);
+        assert_eq!(lexemes(tree.select("',' , 'num'").unwrap()), vec!["1", ",", "2"]);
+        assert_eq!(lexemes(tree.select("list>','").unwrap()), vec![","]);
+    }
+
+    #[test]
+    fn visit_and_fold() {
+        struct Depth(usize, usize);
+        impl Visitor<Tree> for Depth {
+            fn enter(&mut self, _: &Tree) -> bool {
+                self.0 += 1;
+                self.1 = self.1.max(self.0);
+                true
+            }
+            fn leave(&mut self, _: &Tree) { self.0 -= 1; }
+        }
+        let tree = tree();
+        let mut depth = Depth(0, 0);
+        tree.walk(&mut depth);
+        assert_eq!(depth.1, 7);
+
+        struct Eval;
+        impl TreeFold for Eval {
+            type Output = i64;
+            fn leaf(&mut self, _: &str, lexeme: &str) -> i64 {
+                lexeme.parse().unwrap_or(0)
+            }
+            fn node(&mut self, _: &str, subn: Vec<i64>) -> i64 {
+                subn.iter().sum()
+            }
+        }
+        assert_eq!(tree.fold(&mut Eval), 6);
+
+        struct Print;
+        impl SexprFold for Print {
+            type Output = String;
+            fn atom(&mut self, lexeme: &str) -> String { lexeme.to_string() }
+            fn list(&mut self, subn: Vec<String>) -> String {
+                format!("({})", subn.join(" "))
+            }
+        }
+        let sexpr = Sexpr::List(vec![
+            Sexpr::Atom("+".to_string()),
+            Sexpr::List(vec![Sexpr::Atom("1".to_string())]),
+        ]);
+        assert_eq!(sexpr.fold(&mut Print), "(+ (1))");
+    }
+}
@@ -0,0 +1,482 @@
+#![deny(warnings)]
+
+// Serialization of Tree and Sexpr to JSON and canonical S-expressions.
+//
+// JSON shapes:
+//   Tree::Leaf  {"symbol":"num","lexeme":"3"}
+//   Tree::Node  {"rule":"E -> E + num","children":[...]}
+//   Tree::Field {"field":"lhs","tree":{...}}
+//   Sexpr::Atom "3", Sexpr::List [...]
+//
+// S-expressions (Display / FromStr):
+//   Tree  (leaf num 3) (node "E -> E + num" ...) (field lhs ...)
+//   Sexpr (+ 1 (* 2 3))
+// Atoms are written bare unless they have whitespace or delimiters.
+//
+// Trees can also be drawn as Graphviz DOT (output only).
+
+use crate::treeficator::{Sexpr, Tree};
+use lexers::Scanner;
+use std::fmt;
+use std::str::FromStr;
+
+const DELIMITERS: &[char] = &['(', ')', '[', ']', '{', '}', ':', ',', '"'];
+
+#[derive(Clone,Debug,PartialEq)]
+enum Token {
+    Punct(char),
+    Str(String),
+    Bare(String),
+}
+
+// Quote a string escaping as JSON does
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn atom(s: &str) -> String {
+    let bare = !s.is_empty() && !s.chars().any(|c|
+        c.is_whitespace() || c.is_control() || c == '\\' ||
+        DELIMITERS.contains(&c));
+    if bare { s.to_string() } else { quote(s) }
+}
+
+fn hex4(chars: &mut std::str::Chars) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    match u32::from_str_radix(&hex, 16) {
+        Ok(code) if hex.len() == 4 => Ok(code),
+        _ => Err(format!("Bad unicode escape \\u{}", hex)),
+    }
+}
+
+// Undo quote(), input excludes the surrounding quotes
+fn unquote(s: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let mut code = hex4(&mut chars)?;
+                // surrogate pairs come as two escapes
+                if (0xd800..0xdc00).contains(&code) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err("Unpaired surrogate in string".to_string());
+                    }
+                    let low = hex4(&mut chars)?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err("Unpaired surrogate in string".to_string());
+                    }
+                    code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                }
+                out.push(std::char::from_u32(code)
+                    .ok_or_else(|| format!("Bad unicode escape {:x}", code))?);
+            },
+            other => return Err(format!("Bad escape \\{:?}", other)),
+        }
+    }
+    Ok(out)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut s = Scanner::new(input.chars());
+    let mut tokens = Vec::new();
+    loop {
+        s.scan_whitespace();
+        if s.peek().is_none() {
+            return Ok(tokens);
+        }
+        if let Some(p) = s.accept_any(&DELIMITERS[..DELIMITERS.len() - 1]) {
+            s.extract();
+            tokens.push(Token::Punct(p));
+        } else if let Some(q) = s.scan_quoted_string('"') {
+            tokens.push(Token::Str(unquote(&q[1..q.len() - 1])?));
+        } else if s.peek() == Some('"') {
+            return Err("Unterminated string".to_string());
+        } else {
+            while let Some(c) = s.peek() {
+                if c.is_whitespace() || DELIMITERS.contains(&c) {
+                    break;
+                }
+                s.next();
+            }
+            tokens.push(Token::Bare(s.extract_string()));
+        }
+    }
+}
+
+// Cursor over tokens for recursive descent parsing
+struct Tokens(std::iter::Peekable<std::vec::IntoIter<Token>>);
+
+impl Tokens {
+    fn new(input: &str) -> Result<Tokens, String> {
+        Ok(Tokens(tokenize(input)?.into_iter().peekable()))
+    }
+
+    fn expect(&mut self, p: char) -> Result<(), String> {
+        match self.0.next() {
+            Some(Token::Punct(c)) if c == p => Ok(()),
+            other => Err(format!("Expected '{}' found {:?}", p, other)),
+        }
+    }
+
+    fn accept(&mut self, p: char) -> bool {
+        if self.0.peek() == Some(&Token::Punct(p)) {
+            self.0.next();
+            return true;
+        }
+        false
+    }
+
+    // Quoted or bare atom in S-expressions, only quoted in JSON
+    fn string(&mut self, bare: bool) -> Result<String, String> {
+        match self.0.next() {
+            Some(Token::Str(s)) => Ok(s),
+            Some(Token::Bare(s)) if bare => Ok(s),
+            other => Err(format!("Expected a string found {:?}", other)),
+        }
+    }
+
+    fn end(mut self) -> Result<(), String> {
+        match self.0.next() {
+            None => Ok(()),
+            Some(extra) => Err(format!("Unexpected trailing {:?}", extra)),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// S-expressions
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tree::Leaf(symbol, lexeme) =>
+                write!(f, "(leaf {} {})", atom(symbol), atom(lexeme)),
+            Tree::Node(rule, subn) => {
+                write!(f, "(node {}", atom(rule))?;
+                for n in subn {
+                    write!(f, " {}", n)?;
+                }
+                write!(f, ")")
+            },
+            Tree::Field(name, n) => write!(f, "(field {} {})", atom(name), n),
+        }
+    }
+}
+
+impl fmt::Display for Sexpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sexpr::Atom(lexeme) => write!(f, "{}", atom(lexeme)),
+            Sexpr::List(subn) => {
+                write!(f, "(")?;
+                for (idx, n) in subn.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", n)?;
+                }
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+fn tree_sexpr(tokens: &mut Tokens) -> Result<Tree, String> {
+    tokens.expect('(')?;
+    let tree = match tokens.string(true)?.as_str() {
+        "leaf" => Tree::Leaf(tokens.string(true)?, tokens.string(true)?),
+        "node" => {
+            let rule = tokens.string(true)?;
+            let mut subn = Vec::new();
+            while tokens.0.peek() == Some(&Token::Punct('(')) {
+                subn.push(tree_sexpr(tokens)?);
+            }
+            Tree::Node(rule, subn)
+        },
+        "field" => {
+            let name = tokens.string(true)?;
+            Tree::Field(name, Box::new(tree_sexpr(tokens)?))
+        },
+        other => return Err(format!("Unknown tree kind {:?}", other)),
+    };
+    tokens.expect(')')?;
+    Ok(tree)
+}
+
+fn sexpr_sexpr(tokens: &mut Tokens) -> Result<Sexpr, String> {
+    if !tokens.accept('(') {
+        return Ok(Sexpr::Atom(tokens.string(true)?));
+    }
+    let mut subn = Vec::new();
+    while !tokens.accept(')') {
+        subn.push(sexpr_sexpr(tokens)?);
+    }
+    Ok(Sexpr::List(subn))
+}
+
+impl FromStr for Tree {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Tree, String> {
+        let mut tokens = Tokens::new(s)?;
+        let tree = tree_sexpr(&mut tokens)?;
+        tokens.end()?;
+        Ok(tree)
+    }
+}
+
+impl FromStr for Sexpr {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Sexpr, String> {
+        let mut tokens = Tokens::new(s)?;
+        let sexpr = sexpr_sexpr(&mut tokens)?;
+        tokens.end()?;
+        Ok(sexpr)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// JSON
+
+// Parse a JSON object as key/value pairs with a parser for values
+fn json_object<T, F>(tokens: &mut Tokens, mut value: F)
+        -> Result<Vec<(String, T)>, String>
+        where F: FnMut(&str, &mut Tokens) -> Result<T, String> {
+    let mut pairs = Vec::new();
+    tokens.expect('{')?;
+    while !tokens.accept('}') {
+        if !pairs.is_empty() {
+            tokens.expect(',')?;
+        }
+        let key = tokens.string(false)?;
+        tokens.expect(':')?;
+        let v = value(&key, tokens)?;
+        pairs.push((key, v));
+    }
+    Ok(pairs)
+}
+
+fn json_array<T, F>(tokens: &mut Tokens, mut value: F) -> Result<Vec<T>, String>
+        where F: FnMut(&mut Tokens) -> Result<T, String> {
+    let mut items = Vec::new();
+    tokens.expect('[')?;
+    while !tokens.accept(']') {
+        if !items.is_empty() {
+            tokens.expect(',')?;
+        }
+        items.push(value(tokens)?);
+    }
+    Ok(items)
+}
+
+enum TreePart { Str(String), Tree(Tree), Trees(Vec<Tree>) }
+
+fn tree_json(tokens: &mut Tokens) -> Result<Tree, String> {
+    let mut pairs = json_object(tokens, |key, tokens| match key {
+        "children" => Ok(TreePart::Trees(json_array(tokens, tree_json)?)),
+        "tree" => Ok(TreePart::Tree(tree_json(tokens)?)),
+        _ => Ok(TreePart::Str(tokens.string(false)?)),
+    })?;
+    pairs.sort_by(|a, b| a.0.cmp(&b.0));
+    let (keys, parts): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+    let keys: Vec<_> = keys.iter().map(|k| k.as_str()).collect();
+    let mut parts = parts.into_iter();
+    let tree = match (keys.as_slice(), parts.next(), parts.next()) {
+        (["lexeme", "symbol"], Some(TreePart::Str(l)), Some(TreePart::Str(s))) =>
+            Tree::Leaf(s, l),
+        (["children", "rule"], Some(TreePart::Trees(c)), Some(TreePart::Str(r))) =>
+            Tree::Node(r, c),
+        (["field", "tree"], Some(TreePart::Str(f)), Some(TreePart::Tree(t))) =>
+            Tree::Field(f, Box::new(t)),
+        _ => return Err(format!("Bad tree object with keys {:?}", keys)),
+    };
+    Ok(tree)
+}
+
+fn sexpr_json(tokens: &mut Tokens) -> Result<Sexpr, String> {
+    if tokens.0.peek() == Some(&Token::Punct('[')) {
+        return Ok(Sexpr::List(json_array(tokens, sexpr_json)?));
+    }
+    Ok(Sexpr::Atom(tokens.string(false)?))
+}
+
+impl Tree {
+    pub fn to_json(&self) -> String {
+        match self {
+            Tree::Leaf(symbol, lexeme) => format!(
+                "{{\"symbol\":{},\"lexeme\":{}}}", quote(symbol), quote(lexeme)),
+            Tree::Node(rule, subn) => format!(
+                "{{\"rule\":{},\"children\":[{}]}}", quote(rule),
+                subn.iter().map(|n| n.to_json()).collect::<Vec<_>>().join(",")),
+            Tree::Field(name, n) => format!(
+                "{{\"field\":{},\"tree\":{}}}", quote(name), n.to_json()),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Tree, String> {
+        let mut tokens = Tokens::new(json)?;
+        let tree = tree_json(&mut tokens)?;
+        tokens.end()?;
+        Ok(tree)
+    }
+
+    // Graphviz digraph, field labels go on the edge to the child
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph tree {\n");
+        self.dot_helper(&mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    fn dot_helper(&self, ids: &mut usize, out: &mut String) -> usize {
+        let id = *ids;
+        *ids += 1;
+        match self {
+            Tree::Leaf(symbol, lexeme) => out.push_str(&format!(
+                "  n{} [label={}, shape=box];\n", id,
+                quote(&format!("{}\n{}", symbol, lexeme)))),
+            Tree::Node(rule, subn) => {
+                out.push_str(&format!("  n{} [label={}];\n", id, quote(rule)));
+                for n in subn {
+                    let (label, n) = match n {
+                        Tree::Field(name, n) =>
+                            (format!(" [label={}]", quote(name)), n.as_ref()),
+                        n => (String::new(), n),
+                    };
+                    let child = n.dot_helper(ids, out);
+                    out.push_str(&format!("  n{} -> n{}{};\n", id, child, label));
+                }
+            },
+            Tree::Field(_, n) => return n.dot_helper(ids, out),
+        }
+        id
+    }
+}
+
+impl Sexpr {
+    pub fn to_json(&self) -> String {
+        match self {
+            Sexpr::Atom(lexeme) => quote(lexeme),
+            Sexpr::List(subn) => format!("[{}]",
+                subn.iter().map(|n| n.to_json()).collect::<Vec<_>>().join(",")),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Sexpr, String> {
+        let mut tokens = Tokens::new(json)?;
+        let sexpr = sexpr_json(&mut tokens)?;
+        tokens.end()?;
+        Ok(sexpr)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::treeficator::{Sexpr, Tree};
+
+    fn leaf(s: &str, l: &str) -> Tree {
+        Tree::Leaf(s.to_string(), l.to_string())
+    }
+
+    fn tree() -> Tree {
+        Tree::Node("E -> E [+-] num".to_string(), vec![
+            Tree::Field("lhs".to_string(), Box::new(leaf("num", "1"))),
+            leaf("[+-]", "+"),
+            leaf("str", "say \"hi\"\n\tüñí"),
+            Tree::Node("<Uniq-3> -> ".to_string(), vec![]),
+        ])
+    }
+
+    #[test]
+    fn tree_round_trip() {
+        let tree = tree();
+        let sexpr = tree.to_string();
+        assert_eq!(sexpr, concat!(
+            r#"(node "E -> E [+-] num" (field lhs (leaf num 1)) "#,
+            r#"(leaf "[+-]" +) (leaf str "say \"hi\"\n\tüñí") "#,
+            r#"(node "<Uniq-3> -> "))"#));
+        assert_eq!(sexpr.parse::<Tree>(), Ok(tree.clone()));
+        let json = tree.to_json();
+        assert_eq!(json, concat!(
+            r#"{"rule":"E -> E [+-] num","children":["#,
+            r#"{"field":"lhs","tree":{"symbol":"num","lexeme":"1"}},"#,
+            r#"{"symbol":"[+-]","lexeme":"+"},"#,
+            r#"{"symbol":"str","lexeme":"say \"hi\"\n\tüñí"},"#,
+            r#"{"rule":"<Uniq-3> -> ","children":[]}]}"#));
+        assert_eq!(Tree::from_json(&json), Ok(tree));
+        // key order and whitespace don't matter, escapes are decoded
+        let json = r#" { "lexeme" : "ü😀\/" , "symbol":"x" } "#;
+        assert_eq!(Tree::from_json(json), Ok(leaf("x", "ü😀/")));
+    }
+
+    #[test]
+    fn tree_dot() {
+        let tree = Tree::Node("E -> E + num".to_string(), vec![
+            Tree::Field("lhs".to_string(), Box::new(leaf("num", "1"))),
+            leaf("+", "+"),
+        ]);
+        assert_eq!(tree.to_dot(), concat!(
+            "digraph tree {\n",
+            "  n0 [label=\"E -> E + num\"];\n",
+            "  n1 [label=\"num\\n1\", shape=box];\n",
+            "  n0 -> n1 [label=\"lhs\"];\n",
+            "  n2 [label=\"+\\n+\", shape=box];\n",
+            "  n0 -> n2;\n",
+            "}\n"));
+    }
+
+    #[test]
+    fn sexpr_round_trip() {
+        let sexpr = Sexpr::List(vec![
+            Sexpr::Atom("+".to_string()),
+            Sexpr::Atom("(".to_string()),
+            Sexpr::List(vec![]),
+            Sexpr::List(vec![Sexpr::Atom("".to_string()), Sexpr::Atom("a b".to_string())]),
+        ]);
+        assert_eq!(sexpr.to_string(), r#"(+ "(" () ("" "a b"))"#);
+        assert_eq!(sexpr.to_string().parse::<Sexpr>(), Ok(sexpr.clone()));
+        assert_eq!(sexpr.to_json(), r#"["+","(",[],["","a b"]]"#);
+        assert_eq!(Sexpr::from_json(&sexpr.to_json()), Ok(sexpr));
+    }
+
+    #[test]
+    fn bad_input() {
+        for bad in &["", "(leaf a)", "(node x", "(leaf a b) x", "(twig a b)", "(leaf \"a b)"] {
+            assert!(bad.parse::<Tree>().is_err(), "{:?} should fail", bad);
+        }
+        for bad in &["{}", r#"{"symbol":"a"}"#, r#"{"rule":"a","children":{}}"#,
+                     r#"["a",]"#, r#"[1]"#, r#""\x""#, r#""\ud83d""#, r#""\ud800\u0041""#] {
+            assert!(Tree::from_json(bad).is_err(), "{:?} should fail", bad);
+            assert!(Sexpr::from_json(bad).is_err(), "{:?} should fail", bad);
+        }
+        assert!("(a (b)".parse::<Sexpr>().is_err());
+        assert!("a b".parse::<Sexpr>().is_err());
+    }
+}
@@ -1,10 +1,10 @@
 #![deny(warnings)]
 
-use crate::ebnf::ParserBuilder;
-use earlgrey::{EarleyParser, EarleyForest};
+use crate::ebnf::{EbnfError, Fields, ParserBuilder};
+use earlgrey::{EarleyParser, EarleyForest, Grammar};
 use std::fmt::Debug;
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,PartialEq)]
 pub enum Sexpr {
     Atom(String),
     List(Vec<Sexpr>),
@@ -17,6 +17,43 @@ pub enum Tree {
     Leaf(String, String),
     // ("E -> E [+-] E", [...])
     Node(String, Vec<Tree>),
+    // Child labeled in the grammar, ("lhs", ...) for sum := lhs:expr ...
+    Field(String, Box<Tree>),
+}
+
+impl Tree {
+    // Splice nodes of generated helper rules (<Uniq-N>) into their parent,
+    // for example repetitions come out as a flat list of children.
+    pub fn splice_helpers(self) -> Tree {
+        match self {
+            Tree::Node(rule, subn) => Tree::Node(rule, Tree::spliced(subn)),
+            Tree::Field(name, n) => Tree::Field(name, Box::new(n.splice_helpers())),
+            leaf => leaf,
+        }
+    }
+
+    // Child of a Node labeled with 'name' in the grammar
+    pub fn field(&self, name: &str) -> Option<&Tree> {
+        match self {
+            Tree::Node(_, subn) => subn.iter().find_map(|n| match n {
+                Tree::Field(label, n) if label == name => Some(n.as_ref()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn spliced(subn: Vec<Tree>) -> Vec<Tree> {
+        let mut out = Vec::new();
+        for n in subn {
+            match n {
+                Tree::Node(rule, subn) if rule.starts_with("<Uniq-") =>
+                    out.extend(Tree::spliced(subn)),
+                n => out.push(n.splice_helpers()),
+            }
+        }
+        out
+    }
 }
 
 impl Sexpr {
@@ -45,38 +82,60 @@ impl Sexpr {
 
 impl ParserBuilder {
     pub fn treeficator<SI>(self, grammar: &str, start: &str)
-        -> impl Fn(SI) -> Result<Vec<Tree>, String>
+        -> Result<impl Fn(SI) -> Result<Vec<Tree>, String>, EbnfError>
         where SI: Iterator, SI::Item: AsRef<str> + Debug
     {
-        // User may pre-plug grammar (self.0) with terminals
+        // User may pre-plug grammar with terminals
         // 1. build a parser for user's grammar
-        let grammar = ParserBuilder::parse_grammar(self.0, grammar)
-            .unwrap_or_else(|e| panic!("treeficator error: {:?}", e))
-            .into_grammar(start)
-            .unwrap_or_else(|e| panic!("treeficator error: {:?}", e));
+        let (grammar, fields) = self.parse_grammar_fields(grammar)?;
+        let grammar = grammar.into_grammar(start)
+            .map_err(EbnfError::Grammar)?;
         // 2. build evaler that builds trees when executing semantic actions
-        let mut tree_builder = EarleyForest::new(
-            |sym, tok| Tree::Leaf(sym.to_string(), tok.to_string()));
-        for rule in grammar.rules.iter().map(|r| r.to_string()) {
-            tree_builder.action(
-                &rule.clone(), move |nodes| Tree::Node(rule.clone(), nodes));
-        }
+        let tree_builder = ParserBuilder::tree_builder(&grammar, fields);
         // 3. make function that parses strings into trees
         let parser = EarleyParser::new(grammar);
-        move |tokenizer| tree_builder.eval_all(&parser.parse(tokenizer)?)
+        Ok(move |tokenizer| tree_builder.eval_all(&parser.parse(tokenizer)?))
     }
 
     pub fn sexprificator<SI>(self, grammar: &str, start: &str)
-        -> impl Fn(SI) -> Result<Vec<Sexpr>, String>
+        -> Result<impl Fn(SI) -> Result<Vec<Sexpr>, String>, EbnfError>
         where SI: Iterator, SI::Item: AsRef<str> + Debug
     {
-        // User may pre-plug grammar (self.0) with terminals
+        // User may pre-plug grammar with terminals
         // 1. build a parser for user's grammar
-        let grammar = ParserBuilder::parse_grammar(self.0, grammar)
-            .unwrap_or_else(|e| panic!("treeficator error: {:?}", e))
-            .into_grammar(start)
-            .unwrap_or_else(|e| panic!("treeficator error: {:?}", e));
+        let grammar = self.into_grammar(start, grammar)?;
         // 2. build evaler that builds trees when executing semantic actions
+        let tree_builder = ParserBuilder::sexpr_builder(&grammar);
+        // 3. make function that parses strings into trees
+        let parser = EarleyParser::new(grammar);
+        Ok(move |tokenizer| tree_builder.eval_all(&parser.parse(tokenizer)?))
+    }
+
+    // Evaler that builds a Tree for each rule, wrapping labeled children
+    pub(crate) fn tree_builder(grammar: &Grammar, mut fields: Fields)
+            -> EarleyForest<'static, Tree> {
+        let mut tree_builder = EarleyForest::new(
+            |sym, tok| Tree::Leaf(sym.to_string(), tok.to_string()));
+        for rule in grammar.rules.iter().map(|r| r.to_string()) {
+            let labels = fields.remove(&rule).unwrap_or_default();
+            tree_builder.action(&rule.clone(), move |nodes| {
+                // wrap children that have a label
+                let nodes = nodes.into_iter().zip(
+                        labels.iter().cloned().chain(std::iter::repeat(None)))
+                    .map(|(n, label)| match label {
+                        Some(label) => Tree::Field(label, Box::new(n)),
+                        None => n,
+                    })
+                    .collect();
+                Tree::Node(rule.clone(), nodes)
+            });
+        }
+        tree_builder
+    }
+
+    // Evaler that builds nested lists, single child rules are collapsed
+    pub(crate) fn sexpr_builder(grammar: &Grammar)
+            -> EarleyForest<'static, Sexpr> {
         let mut tree_builder = EarleyForest::new(
             |_, tok| Sexpr::Atom(tok.to_string()));
         for rule in &grammar.rules {
@@ -86,8 +145,6 @@ impl ParserBuilder {
                     _ => Sexpr::List(nodes),
                 });
         }
-        // 3. make function that parses strings into trees
-        let parser = EarleyParser::new(grammar);
-        move |tokenizer| tree_builder.eval_all(&parser.parse(tokenizer)?)
+        tree_builder
     }
 }
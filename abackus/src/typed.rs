@@ -0,0 +1,116 @@
+#![deny(warnings)]
+
+// Support for typed ASTs, see abackus-derive's #[derive(Grammar)].
+// Helpers below are used by derived code to read values out of Trees.
+
+use crate::ebnf::{EbnfError, ParserBuilder};
+use crate::treeficator::Tree;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// Types that know their EBNF grammar and how to build themselves from
+/// the Tree of a parse. Usually derived with `#[derive(Grammar)]`.
+pub trait Grammar: Sized {
+    /// Non-terminal for this type in the EBNF spec
+    fn nonterm() -> String;
+    /// Append EBNF rules for this type and the types it uses. `done` maps
+    /// the non-terminals already added to the type they belong to.
+    fn rules(spec: &mut Vec<String>, done: &mut HashMap<String, &'static str>)
+        -> Result<(), String>;
+    /// Build a value out of a Tree node for `nonterm()`
+    fn from_tree(tree: &Tree) -> Result<Self, String>;
+}
+
+// Full EBNF spec for a type, fails if different types use the same
+// non-terminal (eg: same-named types from different modules)
+pub fn grammar_spec<T: Grammar>() -> Result<String, String> {
+    let mut spec = Vec::new();
+    T::rules(&mut spec, &mut HashMap::new())?;
+    Ok(spec.join("\n"))
+}
+
+// Register 'nonterm' for type 'owner', false if it already has its rules
+pub fn claim(done: &mut HashMap<String, &'static str>, nonterm: &str,
+             owner: &'static str) -> Result<bool, String> {
+    match done.insert(nonterm.to_string(), owner) {
+        None => Ok(true),
+        Some(other) if other == owner => Ok(false),
+        Some(other) => Err(format!("Types {} and {} both use non-terminal {}",
+                                   other, owner, nonterm)),
+    }
+}
+
+fn head(tree: &Tree) -> Option<&str> {
+    match tree {
+        Tree::Node(rule, _) => rule.split(" -> ").next(),
+        _ => None,
+    }
+}
+
+// Check tree is a Node for 'nonterm'
+pub fn node<'t>(tree: &'t Tree, nonterm: &str) -> Result<&'t Tree, String> {
+    match head(tree) {
+        Some(h) if h == nonterm => Ok(tree),
+        _ => Err(format!("Expected {} node, got {:?}", nonterm, tree)),
+    }
+}
+
+// Node of the single alternative (variant) matched under 'tree'
+pub fn variant(tree: &Tree) -> Result<(&str, &Tree), String> {
+    if let Tree::Node(_, subn) = tree {
+        if let [alt] = subn.as_slice() {
+            if let Some(h) = head(alt) {
+                return Ok((h, alt));
+            }
+        }
+    }
+    Err(format!("Expected a variant under {:?}", tree))
+}
+
+pub fn field<'t>(tree: &'t Tree, name: &str) -> Result<&'t Tree, String> {
+    tree.field(name)
+        .ok_or_else(|| format!("Missing field {} in {:?}", name, tree))
+}
+
+// Lexeme of a Leaf
+pub fn token(tree: &Tree) -> Result<String, String> {
+    match tree {
+        Tree::Leaf(_, lexeme) => Ok(lexeme.clone()),
+        _ => Err(format!("Expected a token, got {:?}", tree)),
+    }
+}
+
+// Content of an optional helper node: aux -> <e> | X
+pub fn optional(tree: &Tree) -> Result<Option<&Tree>, String> {
+    match tree {
+        Tree::Node(_, subn) if subn.len() < 2 => Ok(subn.first()),
+        _ => Err(format!("Expected an optional, got {:?}", tree)),
+    }
+}
+
+// Elements of a repetition helper node: aux -> <e> | X aux
+pub fn repeated(mut tree: &Tree) -> Result<Vec<&Tree>, String> {
+    let mut elements = Vec::new();
+    loop {
+        match tree {
+            Tree::Node(_, subn) if subn.is_empty() => return Ok(elements),
+            Tree::Node(_, subn) if subn.len() == 2 => {
+                elements.push(&subn[0]);
+                tree = &subn[1];
+            },
+            _ => return Err(format!("Expected a repetition, got {:?}", tree)),
+        }
+    }
+}
+
+impl ParserBuilder {
+    // Build a parser that returns typed values instead of Trees
+    pub fn typed_parser<T, SI>(self)
+        -> Result<impl Fn(SI) -> Result<Vec<T>, String>, EbnfError>
+        where T: Grammar, SI: Iterator, SI::Item: AsRef<str> + Debug
+    {
+        let spec = grammar_spec::<T>().map_err(EbnfError::Grammar)?;
+        let trif = self.treeficator(&spec, &T::nonterm())?;
+        Ok(move |tokenizer| trif(tokenizer)?.iter().map(T::from_tree).collect())
+    }
+}
@@ -1,6 +1,7 @@
 #![deny(warnings)]
 
-use std::collections::HashMap;
+use crate::lr0::Lr0Automaton;
+use std::collections::{HashMap, HashSet};
 use std::{fmt, hash};
 use std::rc::Rc;
 use std::string;
@@ -98,6 +99,22 @@ impl Rule {
 pub struct Grammar {
     pub start: String,
     pub rules: Vec<Rc<Rule>>,
+    automaton: Option<Rc<Lr0Automaton>>,
+}
+
+impl Grammar {
+    /// Precompute an LR(0) automaton for the grammar. Parsers built from a
+    /// compiled grammar track DFA states instead of single dotted rules
+    /// which produces less items and turns predictions into table lookups.
+    /// Compile once and clone the Grammar to share the automaton.
+    pub fn compile(mut self) -> Grammar {
+        self.automaton = Some(Rc::new(Lr0Automaton::new(&self.rules, &self.start)));
+        self
+    }
+
+    pub(crate) fn automaton(&self) -> Option<&Lr0Automaton> {
+        self.automaton.as_ref().map(|a| a.as_ref())
+    }
 }
 
 
@@ -107,6 +124,8 @@ pub struct GrammarBuilder {
     symbols: HashMap<String, Rc<Symbol>>,
     rules: Vec<Rc<Rule>>,
     error: Option<String>,
+    // Terminals that imported grammars may use with their own predicate
+    shared: HashSet<String>,
 }
 
 impl GrammarBuilder {
@@ -195,7 +214,94 @@ impl GrammarBuilder {
         if !self.symbols.contains_key(&start) {
             self.error = Some(format!("Missing Symbol: {}", start));
         }
-        self.error.map_or(Ok(Grammar{start, rules: self.rules}), Err)
+        self.error.map_or(
+            Ok(Grammar{start, rules: self.rules, automaton: None}), Err)
+    }
+
+    /// Allow imported grammars to use a Terminal called `name` even when the
+    /// builder already has a different one. Predicates can't be compared:
+    /// the Terminal already in the builder is kept, so they must match the
+    /// same lexemes.
+    pub fn share_terminal<S>(mut self, name: S) -> Self where S: Into<String> {
+        self.shared.insert(name.into());
+        self
+    }
+
+    /// Add all rules of `grammar` (and the Symbols they use) to the builder.
+    /// NonTerminals must not clash with the ones already in the builder.
+    /// Terminals already in the builder must be the same (eg: from merging
+    /// the same grammar twice) or shared with `share_terminal`.
+    pub fn merge(mut self, grammar: &Grammar) -> Self {
+        self._import(grammar, None);
+        self
+    }
+
+    /// Like `merge` but NonTerminals are namespaced as `prefix::name`
+    /// (eg: `date::month`). The start symbol becomes `prefix::start`.
+    pub fn import(mut self, prefix: &str, grammar: &Grammar) -> Self {
+        self._import(grammar, Some(prefix));
+        self
+    }
+
+    fn _import(&mut self, grammar: &Grammar, prefix: Option<&str>) {
+        // Register NonTerminals under their new names
+        let nonterms = std::iter::once(grammar.start.as_str())
+            .chain(grammar.rules.iter().flat_map(|rule|
+                std::iter::once(rule.head.as_str())
+                    .chain(rule.spec.iter().filter_map(|s| s.nonterm()))));
+        let mut renames = HashMap::new();
+        for name in nonterms {
+            if renames.contains_key(name) { continue }
+            let new_name = match prefix {
+                Some(prefix) => format!("{}::{}", prefix, name),
+                // mechanically generated names are only unique per builder
+                None if name.starts_with("<Uniq-") => self.unique_symbol_name(),
+                None => name.to_string(),
+            };
+            if self.symbols.contains_key(&new_name) {
+                self.error = Some(format!("Duplicate Symbol: {}", new_name));
+                return;
+            }
+            self._add_symbol(Symbol(new_name.clone(), None), false);
+            renames.insert(name, new_name);
+        }
+        // Terminals can't collide with NonTerminals or other Terminals unless
+        // shared (first predicate wins)
+        for symbol in grammar.rules.iter().flat_map(|rule| rule.spec.iter()) {
+            if symbol.terminal().is_none() { continue }
+            match self.symbols.get(symbol.name()) {
+                Some(existing) if existing.nonterm().is_some() => {
+                    self.error = Some(format!("Conflicting Symbol: {}", symbol.name()));
+                    return;
+                },
+                Some(existing) if !Rc::ptr_eq(existing, symbol) &&
+                        !self.shared.contains(symbol.name()) => {
+                    self.error = Some(format!("Conflicting Terminal: {}", symbol.name()));
+                    return;
+                },
+                Some(_) => (),
+                None => {
+                    self.symbols.insert(symbol.name().to_string(), symbol.clone());
+                },
+            }
+        }
+        for rule in &grammar.rules {
+            let spec: Vec<_> = rule.spec.iter()
+                .map(|s| s.nonterm().map_or(s.name(), |n| &renames[n]))
+                .collect();
+            self._add_rule(&renames[rule.head.as_str()], &spec, false);
+        }
+    }
+
+    /// Check if a Terminal or NonTerminal called `name` was already added
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+
+    /// Check if `name` is a Terminal or a NonTerminal with rules
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.symbols.get(name).is_some_and(|symbol| symbol.terminal().is_some() ||
+            self.rules.iter().any(|rule| rule.head == name))
     }
 
     /// Generate unique name for a Symbol (used to build grammar mechanically)
@@ -209,7 +315,7 @@ impl GrammarBuilder {
 
 #[cfg(test)]
 mod tests {
-    use super::{GrammarBuilder, Symbol};
+    use super::{Grammar, GrammarBuilder, Symbol};
     use std::collections::HashMap;
 
     #[test]
@@ -250,6 +356,17 @@ mod tests {
         assert!(g.is_ok());
     }
 
+    #[test]
+    fn defined_symbols() {
+        let gb = GrammarBuilder::default()
+            .nonterm("Sum")
+            .nonterm("Product")
+            .terminal("Num", |n| n == "1")
+            .rule("Sum", &["Num"]);
+        assert!(gb.is_defined("Sum") && gb.is_defined("Num"));
+        assert!(!gb.is_defined("Product") && !gb.is_defined("Nope"));
+    }
+
     #[test]
     fn dup_symbol() {
         let g = GrammarBuilder::default()
@@ -272,6 +389,106 @@ mod tests {
         assert_eq!(g.unwrap_err(), "Duplicate Rule: Sum -> Sum + Num");
     }
 
+    fn digits() -> Grammar {
+        GrammarBuilder::default()
+            .nonterm("Num")
+            .terminal("d", |n| n.chars().all(|c| "123".contains(c)))
+            .rule("Num", &["d"])
+            .rule("Num", &["Num", "d"])
+            .into_grammar("Num")
+            .unwrap()
+    }
+
+    #[test]
+    fn import_grammar() {
+        let g = GrammarBuilder::default()
+            .import("num", &digits())
+            .nonterm("Sum")
+            .terminal("+", |n| n == "+")
+            .rule("Sum", &["num::Num", "+", "num::Num"])
+            .into_grammar("Sum")
+            .unwrap();
+        let rules: Vec<_> = g.rules.iter().map(|r| r.to_string()).collect();
+        assert_eq!(rules, vec![
+            "num::Num -> d",
+            "num::Num -> num::Num d",
+            "Sum -> num::Num + num::Num",
+        ]);
+        // Importing twice under different prefixes shares the terminals
+        let num = digits();
+        let g = GrammarBuilder::default()
+            .import("a", &num)
+            .import("b", &num)
+            .into_grammar("b::Num");
+        assert_eq!(g.unwrap().rules.len(), 4);
+        // A shared Terminal already in the builder keeps its predicate
+        let g = GrammarBuilder::default()
+            .terminal("d", |n| n == "9")
+            .share_terminal("d")
+            .import("num", &digits())
+            .into_grammar("num::Num")
+            .unwrap();
+        let (_, d) = g.rules[0].spec[0].terminal().unwrap();
+        assert!(d("9") && !d("1"));
+    }
+
+    #[test]
+    fn merge_grammar() {
+        let g = GrammarBuilder::default()
+            .merge(&digits())
+            .nonterm("Pair")
+            .rule("Pair", &["Num", "Num"])
+            .into_grammar("Pair")
+            .unwrap();
+        assert_eq!(g.rules.len(), 3);
+        // Generated symbols from different builders don't clash
+        let mut b1 = GrammarBuilder::default();
+        let u1 = b1.unique_symbol_name();
+        b1.quiet_nonterm(u1.clone());
+        b1.quiet_rule(&u1, &[] as &[&str]);
+        let g1 = b1.into_grammar(u1.clone()).unwrap();
+        let g = GrammarBuilder::default()
+            .nonterm(u1)
+            .merge(&g1)
+            .into_grammar("<Uniq-1>")
+            .unwrap();
+        assert_eq!(g.rules[0].to_string(), "<Uniq-1> -> ");
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let g = GrammarBuilder::default()
+            .nonterm("Num")
+            .merge(&digits())
+            .into_grammar("Num");
+        assert_eq!(g.unwrap_err(), "Duplicate Symbol: Num");
+
+        let g = GrammarBuilder::default()
+            .nonterm("d")
+            .import("x", &digits())
+            .into_grammar("d");
+        assert_eq!(g.unwrap_err(), "Conflicting Symbol: d");
+
+        // Same-named Terminals from different grammars may match differently
+        let g = GrammarBuilder::default()
+            .import("x", &digits())
+            .import("y", &digits())
+            .into_grammar("x::Num");
+        assert_eq!(g.unwrap_err(), "Conflicting Terminal: d");
+
+        let g = GrammarBuilder::default()
+            .terminal("d", |n| n == "9")
+            .merge(&digits())
+            .into_grammar("Num");
+        assert_eq!(g.unwrap_err(), "Conflicting Terminal: d");
+
+        let g = GrammarBuilder::default()
+            .import("x", &digits())
+            .import("x", &digits())
+            .into_grammar("x::Num");
+        assert_eq!(g.unwrap_err(), "Duplicate Symbol: x::Num");
+    }
+
     #[test]
     fn missing_symbol() {
         let g = GrammarBuilder::default()
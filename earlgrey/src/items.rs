@@ -74,17 +74,33 @@ impl Item {
 
     /// Scans or Completions that led to the creation of this Item.
     /// only ever borrowed non-mutable ref returned for public consumption
-    pub fn sources(&self) -> cell::Ref<HashSet<(Rc<Item>, Trigger)>> {
+    pub fn sources(&self) -> cell::Ref<'_, HashSet<(Rc<Item>, Trigger)>> {
         self.backpointers.borrow()
     }
 
+    /// Register an extra Scan or Completion that led to this Item.
+    pub fn add_source(&self, source: Rc<Item>, trigger: Trigger) {
+        self.backpointers.borrow_mut().insert((source, trigger));
+    }
+
     /// Merge other Item into this one moving over its backpointers
     pub fn merge_sources(&self, other: Item) {
         assert_eq!(*self, other, "Items to merge should be Eq");
-        let other_bp = other.backpointers.into_inner();
+        let other_bp = other.backpointers.take();
         self.backpointers.borrow_mut().extend(other_bp);
     }
 
+    /// Build an Item without backpointers, sources are added later.
+    pub fn new(rule: &Rc<Rule>, dot: usize, start: usize, end: usize) -> Item {
+        Item{
+            rule: rule.clone(),
+            dot,
+            start,
+            end,
+            backpointers: cell::RefCell::new(HashSet::new()),
+        }
+    }
+
     /// Build a new `Prediction` based Item.
     pub fn predict_new(rule: &Rc<Rule>, start: usize) -> Item {
         Item{
@@ -125,6 +141,142 @@ impl Item {
     }
 }
 
+// Backpointers chain one Item per token, dropping them recursively would
+// overflow the stack on long inputs. Unlink the sources no one else holds.
+impl Drop for Item {
+    fn drop(&mut self) {
+        let mut pending = vec![self.backpointers.take()];
+        while let Some(bp) = pending.pop() {
+            for (source, trigger) in bp {
+                let done = match trigger {
+                    Trigger::Complete(done) => Some(done),
+                    Trigger::Scan(_) => None,
+                };
+                for item in std::iter::once(source).chain(done) {
+                    if let Ok(item) = Rc::try_unwrap(item) {
+                        pending.push(item.backpointers.take());
+                    }
+                }
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+/// How a `StateItem` was reached from its source.
+#[derive(PartialEq,Eq,Hash,Debug,Clone)]
+pub enum Link {
+    /// (terminal, lexeme) scanned
+    Scan(String, String),
+    /// (nonterminal, item) that completed it
+    Complete(String, Rc<StateItem>),
+}
+
+impl Link {
+    /// Symbol the source advanced over
+    pub fn symbol(&self) -> &str {
+        match self {
+            Link::Scan(symbol, _) => symbol,
+            Link::Complete(symbol, _) => symbol,
+        }
+    }
+}
+
+/// An Item of a compiled grammar. Instead of a single dotted rule it points
+/// to a state of the LR(0) automaton, all of its dotted rules share `start`.
+pub struct StateItem {
+    pub state: usize,
+    pub start: usize,
+    pub end: usize,
+    backpointers: cell::RefCell<HashSet<(Rc<StateItem>, Link)>>,
+}
+
+// Like Items, StateItems are deduped ignoring their backpointers
+impl hash::Hash for StateItem {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.state.hash(state);
+        self.start.hash(state);
+        self.end.hash(state);
+    }
+}
+
+impl PartialEq for StateItem {
+    fn eq(&self, other: &StateItem) -> bool {
+        self.state == other.state &&
+        self.start == other.start &&
+        self.end == other.end
+    }
+}
+
+impl Eq for StateItem {}
+
+impl fmt::Debug for StateItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({} - {}) State({}) #bp: {}", self.start, self.end,
+               self.state, self.backpointers.borrow().len())
+    }
+}
+
+// StateItems hash and compare only state, start and end, the backpointers
+// in the RefCell can change while they are keys of a HashSet
+#[allow(clippy::mutable_key_type)]
+impl StateItem {
+    /// Build a `Prediction` (or initial) StateItem.
+    pub fn predict_new(state: usize, start: usize) -> StateItem {
+        StateItem{
+            state,
+            start,
+            end: start,
+            backpointers: cell::RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Build a StateItem by moving `source` to `state` over a Scan/Completion.
+    pub fn advance_new(source: &Rc<StateItem>, state: usize, end: usize, link: Link)
+            -> StateItem {
+        let mut _bp = HashSet::new();
+        _bp.insert((source.clone(), link));
+        StateItem{
+            state,
+            start: source.start,
+            end,
+            backpointers: cell::RefCell::new(_bp),
+        }
+    }
+
+    pub fn sources(&self) -> cell::Ref<'_, HashSet<(Rc<StateItem>, Link)>> {
+        self.backpointers.borrow()
+    }
+
+    /// Merge other StateItem into this one moving over its backpointers
+    pub fn merge_sources(&self, other: StateItem) {
+        assert_eq!(*self, other, "StateItems to merge should be Eq");
+        let other_bp = other.backpointers.take();
+        self.backpointers.borrow_mut().extend(other_bp);
+    }
+}
+
+// Same as Item, unlink long chains of sources without recursion
+impl Drop for StateItem {
+    fn drop(&mut self) {
+        let mut pending = vec![self.backpointers.take()];
+        while let Some(bp) = pending.pop() {
+            for (source, link) in bp {
+                let done = match link {
+                    Link::Complete(_, done) => Some(done),
+                    Link::Scan(..) => None,
+                };
+                for item in std::iter::once(source).chain(done) {
+                    if let Ok(item) = Rc::try_unwrap(item) {
+                        pending.push(item.backpointers.take());
+                    }
+                }
+            }
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
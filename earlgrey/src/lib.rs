@@ -4,6 +4,7 @@ mod grammar;
 pub use grammar::{GrammarBuilder, Grammar};
 
 mod items;
+mod lr0;
 mod parser;
 pub use parser::EarleyParser;
 
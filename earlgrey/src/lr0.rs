@@ -0,0 +1,212 @@
+#![deny(warnings)]
+
+use crate::grammar::{Rule, Symbol};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+
+
+/// A DFA state is a set of LR0 items (dotted rules), encoded as pairs of
+/// (rule index, dot position). Moving over a Symbol is a table lookup.
+pub struct State {
+    items: Vec<(usize, usize)>,
+    /// Non-kernel state holding predictions for this state's next symbols
+    pub predict: Option<usize>,
+    /// Transitions when a NonTerminal completes
+    pub gotos: HashMap<String, usize>,
+    /// Transitions when a Terminal matches the next lexeme
+    pub scans: Vec<(Rc<Symbol>, usize)>,
+    /// Names of rules (heads) that are fully matched in this state
+    pub completes: Vec<String>,
+}
+
+impl State {
+    pub fn contains(&self, rule: usize, dot: usize) -> bool {
+        self.items.binary_search(&(rule, dot)).is_ok()
+    }
+
+    /// Indices of the rules for `head` that are fully matched in this state
+    pub fn completed_rules<'a>(&'a self, rules: &'a [Rc<Rule>], head: &'a str)
+            -> impl Iterator<Item=usize> + 'a {
+        self.items.iter()
+            .filter(move |&&(r, dot)| rules[r].head == head &&
+                                      dot == rules[r].spec.len())
+            .map(|&(r, _)| r)
+    }
+}
+
+/// LR(0) automaton (Aycock-Horspool style) that groups dotted rules that
+/// always move together. Kernel states hold advanced items, their
+/// predictions live in a separate non-kernel state (all dots at 0).
+pub struct Lr0Automaton {
+    pub rules: Vec<Rc<Rule>>,
+    pub states: Vec<State>,
+    pub start: usize,
+}
+
+impl fmt::Debug for Lr0Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lr0Automaton({} states)", self.states.len())
+    }
+}
+
+impl Lr0Automaton {
+    pub fn new(rules: &[Rc<Rule>], start: &str) -> Lr0Automaton {
+        let mut builder = Lr0Builder{
+            rules: rules.to_vec(),
+            states: Vec::new(),
+            index: HashMap::new(),
+        };
+        let start = builder.closure(vec![start]);
+        let start = builder.intern(start);
+        let mut pending = vec![start];
+        while let Some(idx) = pending.pop() {
+            pending.extend(builder.expand(idx));
+        }
+        Lr0Automaton{rules: builder.rules, states: builder.states, start}
+    }
+}
+
+struct Lr0Builder {
+    rules: Vec<Rc<Rule>>,
+    states: Vec<State>,
+    index: HashMap<Vec<(usize, usize)>, usize>,
+}
+
+impl Lr0Builder {
+    /// Dotted rules at position 0 for all rules reachable by prediction
+    fn closure(&self, heads: Vec<&str>) -> Vec<(usize, usize)> {
+        let mut seen: HashSet<&str> = heads.iter().cloned().collect();
+        let mut pending = heads;
+        let mut items = Vec::new();
+        while let Some(head) = pending.pop() {
+            for (idx, rule) in self.rules.iter().enumerate() {
+                if rule.head != head { continue }
+                items.push((idx, 0));
+                if let Some(next) = rule.spec.first().and_then(|s| s.nonterm()) {
+                    if seen.insert(next) {
+                        pending.push(next);
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Find or register a state given its dotted rules
+    fn intern(&mut self, mut items: Vec<(usize, usize)>) -> usize {
+        items.sort();
+        items.dedup();
+        if let Some(&idx) = self.index.get(&items) {
+            return idx;
+        }
+        let completes = items.iter()
+            .filter(|&&(r, dot)| dot == self.rules[r].spec.len())
+            .map(|&(r, _)| self.rules[r].head.clone())
+            .collect::<HashSet<_>>().into_iter().collect();
+        self.states.push(State{
+            items: items.clone(),
+            predict: None,
+            gotos: HashMap::new(),
+            scans: Vec::new(),
+            completes,
+        });
+        self.index.insert(items, self.states.len() - 1);
+        self.states.len() - 1
+    }
+
+    /// Compute predictions and transitions of a newly interned state.
+    /// Returns the states that were created in the process.
+    fn expand(&mut self, idx: usize) -> Vec<usize> {
+        let known = self.states.len();
+        let items = self.states[idx].items.clone();
+        // kernel items (dot > 0) need their predictions in a separate state
+        if items.iter().any(|&(_, dot)| dot > 0) {
+            let heads: Vec<_> = items.iter()
+                .filter_map(|&(r, dot)| self.rules[r].spec.get(dot))
+                .filter_map(|s| s.nonterm())
+                .collect();
+            let predictions = self.closure(heads);
+            if !predictions.is_empty() {
+                self.states[idx].predict = Some(self.intern(predictions));
+            }
+        }
+        // group items by the symbol they advance over, keep input order
+        let mut advances: Vec<(Rc<Symbol>, Vec<_>)> = Vec::new();
+        for &(r, dot) in &items {
+            if let Some(symbol) = self.rules[r].spec.get(dot) {
+                match advances.iter_mut().find(|(s, _)| s == symbol) {
+                    Some((_, kernel)) => kernel.push((r, dot + 1)),
+                    None => advances.push((symbol.clone(), vec![(r, dot + 1)])),
+                }
+            }
+        }
+        for (symbol, kernel) in advances {
+            let target = self.intern(kernel);
+            let state = &mut self.states[idx];
+            if symbol.terminal().is_some() {
+                state.scans.push((symbol, target));
+            } else {
+                state.gotos.insert(symbol.name().to_string(), target);
+            }
+        }
+        (known..self.states.len()).collect()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::GrammarBuilder;
+    use super::Lr0Automaton;
+
+    fn automaton() -> Lr0Automaton {
+        // S -> S + N | N
+        // N -> [0-9]
+        let g = GrammarBuilder::default()
+          .nonterm("S")
+          .nonterm("N")
+          .terminal("[+]", |n| n == "+")
+          .terminal("[0-9]", |n| "1234567890".contains(n))
+          .rule("S", &["S", "[+]", "N"])
+          .rule("S", &["N"])
+          .rule("N", &["[0-9]"])
+          .into_grammar("S")
+          .expect("Bad grammar");
+        Lr0Automaton::new(&g.rules, &g.start)
+    }
+
+    #[test]
+    fn start_state() {
+        let dfa = automaton();
+        let start = &dfa.states[dfa.start];
+        // all 3 rules are predicted from S
+        assert!(start.contains(0, 0) && start.contains(1, 0) && start.contains(2, 0));
+        assert!(start.predict.is_none());
+        assert!(start.completes.is_empty());
+        assert_eq!(start.scans.len(), 1);
+        // completing S moves both S rules forward
+        let after_s = &dfa.states[start.gotos["S"]];
+        assert!(after_s.contains(0, 1));
+        assert!(after_s.completes.is_empty());
+    }
+
+    #[test]
+    fn shared_states() {
+        let dfa = automaton();
+        // start, S ., S + ., N ., S -> S + N ., S -> N ., N -> [0-9] .
+        // plus the non-kernel prediction of N after '+'
+        assert_eq!(dfa.states.len(), 7);
+        let after_plus = dfa.states.iter()
+            .find(|s| s.contains(0, 2))
+            .expect("Missing S -> S + . N");
+        let predict = &dfa.states[after_plus.predict.expect("N predictions")];
+        assert!(predict.contains(2, 0));
+        assert_eq!(predict.scans.len(), 1);
+        // complete states report the rule head
+        let num = &dfa.states[predict.scans[0].1];
+        assert_eq!(num.completes, vec!["N".to_string()]);
+        assert_eq!(num.completed_rules(&dfa.rules, "N").collect::<Vec<_>>(), vec![2]);
+    }
+}
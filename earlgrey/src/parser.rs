@@ -1,8 +1,9 @@
 #![deny(warnings)]
 
 use crate::grammar::{Rule, Grammar};
-use crate::items::Item;
-use std::collections::HashSet;
+use crate::items::{Item, Trigger, StateItem, Link};
+use crate::lr0::Lr0Automaton;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::fmt::Debug;
 
@@ -62,50 +63,60 @@ impl EarleyParser {
         }).map(move |item| Rc::new(Item::scan_new(item, end, lexeme)))
     }
 
+    /// Populate S0, add items for each rule matching the start symbol
+    fn initial_stateset(&self) -> HashSet<Rc<Item>> {
+        self.grammar.rules.iter()
+            .filter(|rule| rule.head == self.grammar.start)
+            .map(|rule| Rc::new(Item::predict_new(&rule, 0)))
+            .collect()
+    }
+
+    /// Predict/Complete until no new Items are added to the StateSet
+    fn expand_stateset(&self, statesets: &mut Vec<HashSet<Rc<Item>>>, idx: usize) {
+        // Instead of looping we could pre-populate completions of nullable symbols
+        loop {
+            let new_items: Vec<_> = statesets[idx].iter().flat_map(|trigger| {
+                let next_sym = trigger.next_symbol();
+                if let Some(next_terminal) = next_sym.and_then(|s| s.nonterm()) {
+                    EarleyParser::predictions(self.grammar.rules.iter(), next_terminal, idx)
+                } else if trigger.complete() {
+                    assert!(next_sym.is_none(), "Expected next symbol to be None");
+                    EarleyParser::completions(statesets[trigger.start].iter(), trigger, idx)
+                } else {
+                    // Scan items populate next stateset only when done with current state
+                    assert!(next_sym.and_then(|s| s.terminal()).is_some());
+                    Box::new(std::iter::empty())
+                }
+            }).collect();
+            let stateset = statesets.get_mut(idx).unwrap();
+            let prev_len = stateset.len();
+            // Add new items to the current stateset merging existing ones
+            for new_item in new_items {
+                if let Some(existent) = stateset.get(&new_item) {
+                    existent.merge_sources(new_item);
+                } else {
+                    stateset.insert(Rc::new(new_item));
+                }
+            }
+            // do precitions/completions until expansions are exhausted
+            if prev_len == stateset.len() {
+                break;
+            }
+        }
+    }
+
     pub fn parse<T>(&self, mut tokenizer: T) -> Result<ParseTrees, String>
             where T: Iterator, T::Item: Debug + AsRef<str> {
 
-        // Populate S0, add items for each rule matching the start symbol
-        let s0: HashSet<_> = self.grammar.rules.iter()
-            .filter(|rule| rule.head == self.grammar.start)
-            .map(|rule| Rc::new(Item::predict_new(&rule, 0)))
-            .collect();
+        if let Some(automaton) = self.grammar.automaton() {
+            return self.parse_compiled(automaton, tokenizer);
+        }
 
-        let mut statesets = vec![s0];
+        let mut statesets = vec![self.initial_stateset()];
 
         // New statesets are generated from input stream (Scans)
         for idx in 0.. {
-            // Predict/Complete until no new Items are added to the StateSet
-            // Instead of looping we could pre-populate completions of nullable symbols
-            loop {
-                let new_items: Vec<_> = statesets[idx].iter().flat_map(|trigger| {
-                    let next_sym = trigger.next_symbol();
-                    if let Some(next_terminal) = next_sym.and_then(|s| s.nonterm()) {
-                        EarleyParser::predictions(self.grammar.rules.iter(), next_terminal, idx)
-                    } else if trigger.complete() {
-                        assert!(next_sym.is_none(), "Expected next symbol to be None");
-                        EarleyParser::completions(statesets[trigger.start].iter(), trigger, idx)
-                    } else {
-                        // Scan items populate next stateset only when done with current state
-                        assert!(next_sym.and_then(|s| s.terminal()).is_some());
-                        Box::new(std::iter::empty())
-                    }
-                }).collect();
-                let stateset = statesets.get_mut(idx).unwrap();
-                let prev_len = stateset.len();
-                // Add new items to the current stateset merging existing ones
-                for new_item in new_items {
-                    if let Some(existent) = stateset.get(&new_item) {
-                        existent.merge_sources(new_item);
-                    } else {
-                        stateset.insert(Rc::new(new_item));
-                    }
-                }
-                // do precitions/completions until expansions are exhausted
-                if prev_len == stateset.len() {
-                    break;
-                }
-            }
+            self.expand_stateset(&mut statesets, idx);
             // Build Si+1 with items in the current state that accept the next token
             if let Some(lexeme) = tokenizer.next() {
                 statesets.push(EarleyParser::scans(
@@ -143,4 +154,214 @@ impl EarleyParser {
         }
         Ok(ParseTrees(parse_trees))
     }
+
+    /// Find where `parse` fails. Returns the index of the first token that
+    /// can't be scanned (or the input length if input ended too early) and
+    /// the names of the Terminals that were expected there.
+    /// Returns None if the input can be parsed.
+    // Items in the statesets hash ignoring their backpointers (see Item)
+    #[allow(clippy::mutable_key_type)]
+    pub fn diagnose<T>(&self, mut tokenizer: T) -> Option<(usize, Vec<String>)>
+            where T: Iterator, T::Item: AsRef<str> {
+        let mut statesets = vec![self.initial_stateset()];
+        for idx in 0.. {
+            self.expand_stateset(&mut statesets, idx);
+            let stateset = &statesets[idx];
+            let next: HashSet<_> = match tokenizer.next() {
+                Some(lexeme) => EarleyParser::scans(
+                    stateset.iter(), lexeme.as_ref(), idx + 1).collect(),
+                None if stateset.iter().any(|item| item.start == 0 &&
+                        item.complete() && item.rule.head == self.grammar.start)
+                    => return None,
+                None => HashSet::new(),
+            };
+            if next.is_empty() {
+                let mut expected: Vec<_> = stateset.iter()
+                    .filter_map(|item| item.next_symbol().and_then(|s| s.terminal()))
+                    .map(|(name, _)| name.to_string())
+                    .collect::<HashSet<_>>().into_iter().collect();
+                expected.sort();
+                return Some((idx, expected));
+            }
+            statesets.push(next);
+        }
+        unreachable!()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+// Parsing with a compiled grammar (LR(0) automaton states instead of rules)
+// StateItems are keys despite their RefCell, their hash ignores backpointers
+#[allow(clippy::mutable_key_type)]
+impl EarleyParser {
+    /// Add `item` to the stateset queueing it for processing if it's new
+    fn add_state_item(stateset: &mut HashSet<Rc<StateItem>>,
+                      pending: &mut Vec<Rc<StateItem>>, item: StateItem) {
+        if let Some(existent) = stateset.get(&item) {
+            existent.merge_sources(item);
+        } else {
+            let item = Rc::new(item);
+            stateset.insert(item.clone());
+            pending.push(item);
+        }
+    }
+
+    fn parse_compiled<T>(&self, dfa: &Lr0Automaton, mut tokenizer: T)
+            -> Result<ParseTrees, String>
+            where T: Iterator, T::Item: Debug + AsRef<str> {
+
+        let mut statesets: Vec<HashSet<Rc<StateItem>>> = Vec::new();
+        let mut scanned = vec![StateItem::predict_new(dfa.start, 0)];
+
+        for idx in 0.. {
+            let mut stateset = HashSet::new();
+            let mut pending = Vec::new();
+            for item in scanned {
+                EarleyParser::add_state_item(&mut stateset, &mut pending, item);
+            }
+            // Nullable symbols completed at idx (items that start at idx)
+            // that need to advance items added later to this same stateset
+            let mut nulled: HashMap<&str, Vec<Rc<StateItem>>> = HashMap::new();
+
+            while let Some(item) = pending.pop() {
+                let state = &dfa.states[item.state];
+                let mut new_items = Vec::new();
+                // Predictions are precomputed, just add the non-kernel state
+                if let Some(predict) = state.predict {
+                    new_items.push(StateItem::predict_new(predict, idx));
+                }
+                // Advance items in the starting stateset waiting for heads
+                for head in &state.completes {
+                    let parents: Vec<_> = if item.start == idx {
+                        nulled.entry(head).or_default().push(item.clone());
+                        stateset.iter().cloned().collect()
+                    } else {
+                        statesets[item.start].iter().cloned().collect()
+                    };
+                    for parent in parents {
+                        if let Some(&to) = dfa.states[parent.state].gotos.get(head) {
+                            new_items.push(StateItem::advance_new(
+                                &parent, to, idx, Link::Complete(head.clone(), item.clone())));
+                        }
+                    }
+                }
+                // This item may be waiting on a nullable symbol already completed
+                for (symbol, &to) in &state.gotos {
+                    for done in nulled.get(symbol.as_str()).into_iter().flatten() {
+                        new_items.push(StateItem::advance_new(
+                            &item, to, idx, Link::Complete(symbol.clone(), done.clone())));
+                    }
+                }
+                for new_item in new_items {
+                    EarleyParser::add_state_item(&mut stateset, &mut pending, new_item);
+                }
+            }
+            statesets.push(stateset);
+
+            // Build Si+1 with items in the current state that accept the next token
+            if let Some(lexeme) = tokenizer.next() {
+                let lexeme = lexeme.as_ref();
+                scanned = statesets[idx].iter().flat_map(|item| {
+                    dfa.states[item.state].scans.iter()
+                        .filter(move |(symbol, _)| symbol.terminal()
+                                .map(|(_, matcher)| matcher(lexeme)) == Some(true))
+                        .map(move |(symbol, to)| StateItem::advance_new(
+                            item, *to, idx + 1,
+                            Link::Scan(symbol.name().to_string(), lexeme.to_string())))
+                }).collect();
+            } else {
+                break;
+            }
+        }
+
+        // debug StateSets
+        if cfg!(feature="debug") {
+            for (idx, stateset) in statesets.iter().enumerate() {
+                eprintln!("=== StateSet {} ===", idx);
+                stateset.iter().inspect(|item| {
+                    let src = item.sources().iter()
+                        .map(|(s, l)| format!("Src({:?}) + {:?}", s, l))
+                        .collect::<Vec<_>>().join(", ");
+                    eprintln!("{:?} -- SRC: {}", item, src);
+                }).count();
+            }
+        }
+
+        // Same checks as the non-compiled version, then translate completed
+        // items back to dotted rules so EarleyForest can walk them
+        let start = &self.grammar.start;
+        let mut expander = Expander{dfa, items: HashMap::new(), seen: HashSet::new()};
+        let mut parse_trees: Vec<Rc<Item>> = Vec::new();
+        for root in statesets.last().expect("No Statesets (even s0)") {
+            if root.start != 0 { continue }
+            for rule in dfa.states[root.state].completed_rules(&dfa.rules, start) {
+                let tree = expander.expand(root, rule, dfa.rules[rule].spec.len());
+                if !parse_trees.iter().any(|t| Rc::ptr_eq(t, &tree)) {
+                    parse_trees.push(tree);
+                }
+            }
+        }
+        if parse_trees.is_empty() {
+            return Err("Parse Error: No Rule completes".to_string());
+        }
+        Ok(ParseTrees(parse_trees))
+    }
+}
+
+/// Rebuilds dotted rule `Item`s (and their backpointers) out of `StateItem`s.
+/// Only items reachable from the completed roots are visited.
+struct Expander<'a> {
+    dfa: &'a Lr0Automaton,
+    // (rule, dot, start, end) -> Item. Same key as Item equality.
+    items: HashMap<(usize, usize, usize, usize), Rc<Item>>,
+    // (StateItem, rule, dot) whose backpointers were already translated
+    seen: HashSet<(*const StateItem, usize, usize)>,
+}
+
+impl<'a> Expander<'a> {
+    fn expand(&mut self, root: &Rc<StateItem>, rule: usize, dot: usize) -> Rc<Item> {
+        let dfa = self.dfa;
+        // An explicit stack, long inputs make source chains too deep to recurse
+        let mut pending = Vec::new();
+        let tree = self.item(root, rule, dot, &mut pending);
+        while let Some((sitem, rule, dot, item)) = pending.pop() {
+            let symbol = dfa.rules[rule].spec[dot - 1].name();
+            for (source, link) in sitem.sources().iter() {
+                if link.symbol() != symbol || !dfa.states[source.state].contains(rule, dot - 1) {
+                    continue;
+                }
+                let source_item = self.item(source, rule, dot - 1, &mut pending);
+                match link {
+                    Link::Scan(_, lexeme) =>
+                        item.add_source(source_item, Trigger::Scan(lexeme.clone())),
+                    Link::Complete(head, done) => {
+                        let completed: Vec<_> = dfa.states[done.state]
+                            .completed_rules(&dfa.rules, head).collect();
+                        for r in completed {
+                            let dot = dfa.rules[r].spec.len();
+                            let trigger = self.item(done, r, dot, &mut pending);
+                            item.add_source(source_item.clone(), Trigger::Complete(trigger));
+                        }
+                    }
+                }
+            }
+        }
+        tree
+    }
+
+    /// The Item for a dotted rule of `sitem`, queueing its backpointers to be
+    /// translated the first time it's seen
+    fn item(&mut self, sitem: &Rc<StateItem>, rule: usize, dot: usize,
+            pending: &mut Vec<(Rc<StateItem>, usize, usize, Rc<Item>)>) -> Rc<Item> {
+        let dfa = self.dfa;
+        let key = (rule, dot, sitem.start, sitem.end);
+        let item = self.items.entry(key).or_insert_with(|| Rc::new(
+            Item::new(&dfa.rules[rule], dot, sitem.start, sitem.end))).clone();
+        // Different StateItems may hold the same dotted rule, merge them all
+        if dot > 0 && self.seen.insert((Rc::as_ptr(sitem), rule, dot)) {
+            pending.push((sitem.clone(), rule, dot, item.clone()));
+        }
+        item
+    }
 }
@@ -298,6 +298,38 @@ fn math_ambiguous_catalan() {
     assert_eq!(trees.len(), 42);
 }
 
+#[test]
+fn composed_grammar() {
+    // Pair -> num::Num , num::Num
+    // num::Num -> d | num::Num d
+    let digits = GrammarBuilder::default()
+      .nonterm("Num")
+      .terminal("d", |n| "1234567890".contains(n))
+      .rule("Num", &["d"])
+      .rule("Num", &["Num", "d"])
+      .into_grammar("Num")
+      .expect("Bad grammar");
+    let grammar = GrammarBuilder::default()
+      .import("num", &digits)
+      .nonterm("Pair")
+      .terminal(",", |n| n == ",")
+      .rule("Pair", &["num::Num", ",", "num::Num"])
+      .into_grammar("Pair")
+      .expect("Bad grammar");
+    let p = EarleyParser::new(grammar.clone());
+    let pout = p.parse("1 2 , 3".split_whitespace()).unwrap();
+    let tree = tree_evaler(grammar).eval(&pout).unwrap();
+    check_trees(&vec![tree], vec![
+        concat!(
+            r#"Node("Pair -> num::Num , num::Num", ["#,
+                r#"Node("num::Num -> num::Num d", ["#,
+                    r#"Node("num::Num -> d", [Leaf("d", "1")]), "#,
+                    r#"Leaf("d", "2")]), "#,
+                r#"Leaf(",", ","), "#,
+                r#"Node("num::Num -> d", [Leaf("d", "3")])])"#)
+    ]);
+}
+
 mod small_math {
     use crate::grammar::{Grammar, GrammarBuilder};
     use crate::parser::EarleyParser;
@@ -384,13 +416,19 @@ mod earley_recognizer {
     use crate::grammar::GrammarBuilder;
     use super::EarleyParser;
 
+    // Check the input with the parser and its compiled counterpart
     fn good(parser: &EarleyParser, input: &str) {
         assert!(parser.parse(input.split_whitespace()).is_ok());
+        let compiled = EarleyParser::new(parser.grammar.clone().compile());
+        assert!(compiled.parse(input.split_whitespace()).is_ok());
     }
 
     fn fail(parser: &EarleyParser, input: &str) {
         assert_eq!(parser.parse(input.split_whitespace()).unwrap_err(),
                    "Parse Error: No Rule completes");
+        let compiled = EarleyParser::new(parser.grammar.clone().compile());
+        assert_eq!(compiled.parse(input.split_whitespace()).unwrap_err(),
+                   "Parse Error: No Rule completes");
     }
 
     #[test]
@@ -440,6 +478,30 @@ mod earley_recognizer {
         fail(&p, "+ 3");
     }
 
+    #[test]
+    fn diagnose() {
+        // S -> S + N | N
+        // N -> [0-9]
+        let grammar = GrammarBuilder::default()
+          .nonterm("S")
+          .nonterm("N")
+          .terminal("[+]", |n| n == "+")
+          .terminal("[0-9]", |n| "1234567890".contains(n))
+          .rule("S", &["S", "[+]", "N"])
+          .rule("S", &["N"])
+          .rule("N", &["[0-9]"])
+          .into_grammar("S")
+          .expect("Bad grammar");
+        let p = EarleyParser::new(grammar);
+        let diagnose = |input: &str| p.diagnose(input.split_whitespace());
+        assert_eq!(diagnose("1 + 2"), None);
+        assert_eq!(diagnose("1 2 + 3"), Some((1, vec!["[+]".to_string()])));
+        assert_eq!(diagnose("+ 3"), Some((0, vec!["[0-9]".to_string()])));
+        // input ends early
+        assert_eq!(diagnose("1 +"), Some((2, vec!["[0-9]".to_string()])));
+        assert_eq!(diagnose(""), Some((0, vec!["[0-9]".to_string()])));
+    }
+
     #[test]
     fn right_recurse() {
         // P -> N ^ P | N
@@ -581,6 +643,118 @@ mod earley_recognizer {
           .expect("Bad grammar");
         let p = EarleyParser::new(grammar);
         let input = "containsmainword".chars().map(|c| c.to_string());
-        assert!(p.parse(input).is_ok());
+        assert!(p.parse(input.clone()).is_ok());
+        let compiled = EarleyParser::new(p.grammar.clone().compile());
+        assert!(compiled.parse(input).is_ok());
+    }
+}
+
+mod compiled {
+    use crate::grammar::{Grammar, GrammarBuilder};
+    use crate::parser::EarleyParser;
+    use super::tree_evaler;
+
+    // Compiled grammars should generate exactly the same trees
+    fn same_trees(grammar: Grammar, input: &str) -> usize {
+        let evaler = tree_evaler(grammar.clone());
+        let parser = EarleyParser::new(grammar.clone());
+        let pout = parser.parse(input.split_whitespace()).unwrap();
+        let mut trees = evaler.eval_all(&pout).unwrap()
+            .iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>();
+        let parser = EarleyParser::new(grammar.compile());
+        let pout = parser.parse(input.split_whitespace()).unwrap();
+        let mut ctrees = evaler.eval_all(&pout).unwrap()
+            .iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>();
+        trees.sort();
+        ctrees.sort();
+        assert_eq!(trees, ctrees);
+        trees.len()
+    }
+
+    #[test]
+    fn catalan() {
+        let grammar = GrammarBuilder::default()
+          .nonterm("E")
+          .terminal("+", |n| n == "+")
+          .terminal("n", |n| "1234567890".contains(n))
+          .rule("E", &["E", "+", "E"])
+          .rule("E", &["n"])
+          .into_grammar("E")
+          .expect("Bad grammar");
+        assert_eq!(same_trees(grammar, "0 + 1 + 2 + 3 + 4 + 5"), 42);
+    }
+
+    #[test]
+    fn ambiguous_epsilon() {
+        // S -> SSX | b
+        // X -> <e>
+        let grammar = GrammarBuilder::default()
+          .nonterm("S")
+          .nonterm("X")
+          .terminal("b", |n| n == "b")
+          .rule("S", &["S", "S", "X"])
+          .rule::<_, String>("X", &[])
+          .rule("S", &["b"])
+          .into_grammar("S")
+          .expect("Bad grammar");
+        assert_eq!(same_trees(grammar, "b b b"), 2);
+    }
+
+    #[test]
+    fn shared_prefix() {
+        // Rules sharing a prefix land in the same automaton states
+        // S -> A x | A y | B ; A -> a ; B -> A
+        let grammar = GrammarBuilder::default()
+          .nonterm("S")
+          .nonterm("A")
+          .nonterm("B")
+          .terminal("a", |n| n == "a")
+          .terminal("x", |n| n == "x")
+          .terminal("y", |n| n == "y")
+          .rule("S", &["A", "x"])
+          .rule("S", &["A", "y"])
+          .rule("S", &["B"])
+          .rule("A", &["a"])
+          .rule("B", &["A"])
+          .into_grammar("S")
+          .expect("Bad grammar");
+        assert_eq!(same_trees(grammar.clone(), "a x"), 1);
+        assert_eq!(same_trees(grammar.clone(), "a y"), 1);
+        assert_eq!(same_trees(grammar, "a"), 1);
+    }
+
+    #[test]
+    fn epsilon_balanced() {
+        // P  -> '(' P ')' | P P | <epsilon>
+        let grammar = GrammarBuilder::default()
+          .nonterm("P")
+          .terminal("(", |l| l == "(")
+          .terminal(")", |l| l == ")")
+          .rule("P", &["(", "P", ")"])
+          .rule("P", &["P", "P"])
+          .rule::<_, String>("P", &[])
+          .into_grammar("P")
+          .expect("Bad grammar")
+          .compile();
+        // Infinitely ambiguous, only check recognition
+        let p = EarleyParser::new(grammar);
+        assert!(p.parse("( ( ) ( ) )".split_whitespace()).is_ok());
+        assert!(p.parse("( ( ) ) )".split_whitespace()).is_err());
+    }
+
+    #[test]
+    fn long_input() {
+        // Items chain one source per token, building the trees or dropping
+        // them mustn't recurse
+        let grammar = GrammarBuilder::default()
+          .nonterm("L")
+          .terminal("x", |n| n == "x")
+          .rule("L", &["L", "x"])
+          .rule("L", &["x"])
+          .into_grammar("L")
+          .expect("Bad grammar")
+          .compile();
+        let p = EarleyParser::new(grammar);
+        assert!(p.parse(std::iter::repeat("x").take(50_000)).is_ok());
     }
 }
@@ -0,0 +1,8 @@
+include!("src/plugged.rs");
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/plugged.rs");
+    if let Err(e) = abackus::codegen::generate_file("src/time.ebnf", "time", PLUGGED) {
+        panic!("{}", e);
+    }
+}
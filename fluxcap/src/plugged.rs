@@ -0,0 +1,4 @@
+// Terminals of time.ebnf plugged by time_parser::_parser_builder. build.rs
+// includes this file too, the generated grammar code expects them plugged.
+const PLUGGED: &[&str] = &["ordinal", "day_ordinal", "weekday", "month", "grain",
+                           "year", "small_int"];
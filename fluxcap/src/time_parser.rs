@@ -4,66 +4,16 @@
 // https://github.com/wit-ai/duckling_old/blob/master/resources/languages/en/rules/time.clj
 
 pub fn time_grammar() -> &'static str {
-    r#"
-    named_seq := day_ordinal
-              | weekday
-              | month
-              | day_ordinal 'of' month
-              | month day_ordinal
-              | weekday day_ordinal
-              | weekday day_ordinal 'of' month
-              | weekday month day_ordinal
-              | 'weekend' | 'weekends'
-              ;
-
-    sequence := named_seq | grain;
-
-    comp_seq := ordinal sequence 'of' ['the'] @opt_the comp_seq
-              | 'last' sequence 'of' ['the'] @opt_the comp_seq
-              | sequence
-              ;
-
-    comp_grain := small_int grain
-               | 'a' grain
-               | comp_grain 'and' small_int grain
-               | comp_grain 'and' 'a' grain
-               ;
-
-    time := 'today'
-          | 'tomorrow'
-          | 'yesterday'
-          | 'on' weekday
-          | named_seq
-
-          | 'the' comp_seq
-          | 'this' comp_seq
-          | 'next' comp_seq
-          | 'last' comp_seq
-
-          | comp_seq 'after' 'next'
-          | comp_seq 'before' 'last'
-
-          | 'a' named_seq 'ago'
-          |  small_int named_seq 'ago'
-          | 'in' small_int named_seq
-
-          | comp_grain 'ago'
-          | 'in' comp_grain
-
-          | year
-          | month year
-          | month day_ordinal year
-
-          | comp_grain 'after' time
-          | comp_grain 'before' time
+    include_str!("time.ebnf")
+}
 
-          | sequence 'until' time
-          | sequence 'since' time
-          | sequence 'between' time 'and' time
-          ;
-    "#
+// GrammarBuilder code generated from time.ebnf by build.rs
+mod time {
+    include!(concat!(env!("OUT_DIR"), "/time.rs"));
 }
 
+include!("plugged.rs");
+
 fn _parser_builder() -> abackus::ParserBuilder {
     use std::str::FromStr;
     use crate::constants::*;
@@ -80,13 +30,18 @@ fn _parser_builder() -> abackus::ParserBuilder {
 }
 
 pub fn time_parser() -> earlgrey::EarleyParser {
-    _parser_builder()
-        .into_parser("time", time_grammar())
-        .unwrap_or_else(|e| panic!("TimeMachine grammar BUG: {:?}", e))
+    let gb = _parser_builder().into_grammar_builder();
+    debug_assert!(PLUGGED.iter().all(|t| gb.has_symbol(t)));
+    let grammar = time::grammar_builder(gb)
+        .into_grammar(time::START)
+        .unwrap_or_else(|e| panic!("TimeMachine grammar BUG: {:?}", e));
+    earlgrey::EarleyParser::new(grammar)
 }
 
 pub fn debug_time_expression(time: &str) -> Result<Vec<abackus::Sexpr>, String> {
     let tokenizer = lexers::DelimTokenizer::new(time.chars(), ", ", true);
-    let sexpr_writer = _parser_builder().sexprificator(time_grammar(), "time");
+    let sexpr_writer = _parser_builder()
+        .sexprificator(time_grammar(), "time")
+        .map_err(|e| e.to_string())?;
     sexpr_writer(tokenizer)
 }
@@ -1,30 +1,281 @@
 #![deny(warnings)]
 
 use crate::scanner::Scanner;
+use crate::span::{Position, Span};
+use unicode_xid::UnicodeXID;
 
-static WHITE: &[char] = &[' ', '\n', '\r', '\t'];
+pub(crate) static WHITE: &[char] = &[' ', '\n', '\r', '\t'];
 static DIGITS: &[char] = &['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
 static HEXDIGITS: &[char] = &[
     '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f', 'A', 'B', 'C',
     'D', 'E', 'F',
 ];
-static ALPHA: &[char] = &[
-    '_', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
-    's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K',
-    'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
-];
-static ALNUM: &[char] = &[
-    '_', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h',
-    'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'A',
-    'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T',
-    'U', 'V', 'W', 'X', 'Y', 'Z',
-];
+// full-width digits ０-９ accepted by NumberStyle::full_width
+static FULL_DIGITS: &[char] = &['０', '１', '２', '３', '４', '５', '６', '７', '８', '９'];
+
+/// Opt-in extras for `scan_number_with`, `scan_number` takes neither
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumberStyle {
+    /// Allow '_' between digits like 1_000_000
+    pub separators: bool,
+    /// Allow full-width digits like １２３
+    pub full_width: bool,
+}
+
+impl NumberStyle {
+    /// Separators and full-width digits
+    pub fn all() -> Self {
+        NumberStyle { separators: true, full_width: true }
+    }
+
+    /// A scanned number without separators and with ASCII digits, so
+    /// str::parse can read it
+    pub fn normalize(number: &str) -> String {
+        number
+            .chars()
+            .filter(|&c| c != '_')
+            .map(|c| match FULL_DIGITS.iter().position(|&d| d == c) {
+                Some(d) => DIGITS[d],
+                None => c,
+            })
+            .collect()
+    }
+}
+
+// Cursor over chars the scan_* matchers are written against, so char
+// Scanners and StrScanner share them
+pub(crate) trait CharCursor {
+    type Pos: Copy;
+    fn next_char(&mut self) -> Option<char>;
+    fn pos(&self) -> Self::Pos;
+    fn set_pos(&mut self, pos: Self::Pos);
+
+    fn accept_char(&mut self, any: &[char]) -> Option<char> {
+        self.accept_if(|c| any.contains(&c))
+    }
+
+    fn accept_if<F: Fn(char) -> bool>(&mut self, pred: F) -> Option<char> {
+        let backtrack = self.pos();
+        match self.next_char() {
+            Some(c) if pred(c) => Some(c),
+            _ => {
+                self.set_pos(backtrack);
+                None
+            }
+        }
+    }
+
+    fn skip_chars(&mut self, over: &[char]) -> bool {
+        let mut advanced = false;
+        while self.accept_char(over).is_some() {
+            advanced = true;
+        }
+        advanced
+    }
+}
+
+impl<I: Iterator<Item = char>> CharCursor for Scanner<I> {
+    type Pos = isize;
+    fn next_char(&mut self) -> Option<char> {
+        self.next()
+    }
+    fn pos(&self) -> isize {
+        self.buffer_pos()
+    }
+    fn set_pos(&mut self, pos: isize) {
+        self.set_buffer_pos(pos);
+    }
+}
+
+// The matchers leave the cursor after what they matched and return if
+// they did, otherwise they restore it
+
+// one or more digits, '_' only between them if the style allows it
+fn digits<C: CharCursor>(s: &mut C, style: NumberStyle) -> bool {
+    let digit = |c: char| DIGITS.contains(&c) || style.full_width && FULL_DIGITS.contains(&c);
+    if s.accept_if(digit).is_none() {
+        return false;
+    }
+    loop {
+        let backtrack = s.pos();
+        if style.separators {
+            s.accept_char(&['_']);
+        }
+        if s.accept_if(digit).is_none() {
+            s.set_pos(backtrack);
+            return true;
+        }
+    }
+}
+
+// numbers like -?[0-9]+(\.[0-9]+)?([eE][+-][0-9]+)?
+pub(crate) fn number<C: CharCursor>(s: &mut C, style: NumberStyle) -> bool {
+    let backtrack = s.pos();
+    // optional sign
+    s.accept_char(&['+', '-']);
+    // require integer part
+    if !digits(s, style) {
+        s.set_pos(backtrack);
+        return false;
+    }
+    // check for fractional part, else it's just an integer
+    let backtrack = s.pos();
+    if s.accept_char(&['.']).is_some() && !digits(s, style) {
+        s.set_pos(backtrack);
+        return true; // integer
+    }
+    // check for exponent part
+    let backtrack = s.pos();
+    if s.accept_char(&['e', 'E']).is_some() {
+        s.accept_char(&['+', '-']); // exponent sign is optional
+        if !digits(s, style) {
+            s.set_pos(backtrack);
+            return true; // float
+        }
+    }
+    s.accept_char(&['i']); // accept imaginary numbers
+    true
+}
+
+pub(crate) fn math_op<C: CharCursor>(s: &mut C) -> bool {
+    const OPS: &[char] = &['+', '-', '*', '/', '%', '^', '!', '(', ')', ','];
+    if s.accept_char(&['>', '=', '<']).is_some() {
+        // accept '<', '>', '=', '<=', '>=', '=='
+        s.accept_char(&['=']);
+        true
+    } else if s.accept_char(&['*']).is_some() {
+        // accept '*', '**'
+        s.accept_char(&['*']);
+        true
+    } else {
+        s.accept_char(OPS).is_some()
+    }
+}
+
+// integers like 0x34 0b10101 0o657
+pub(crate) fn integer<C: CharCursor>(s: &mut C) -> bool {
+    let backtrack = s.pos();
+    if s.accept_char(&['0']).is_some()
+        && match s.accept_char(&['x', 'o', 'b']) {
+            Some('x') => s.skip_chars(HEXDIGITS),
+            Some('o') => s.skip_chars(&HEXDIGITS[..8]),
+            Some('b') => s.skip_chars(&HEXDIGITS[..2]),
+            _ => false,
+        }
+    {
+        return true;
+    }
+    s.set_pos(backtrack);
+    false
+}
+
+// a quoted string like "this is \"an\" example"
+pub(crate) fn quoted_string<C: CharCursor>(s: &mut C, q: char) -> bool {
+    let backtrack = s.pos();
+    if s.accept_char(&[q]).is_none() {
+        return false;
+    }
+    while let Some(n) = s.next_char() {
+        if n == '\\' {
+            s.next_char();
+            continue;
+        }
+        if n == q {
+            return true;
+        }
+    }
+    s.set_pos(backtrack);
+    false
+}
+
+// Why a quoted string can't be decoded
+pub(crate) enum StringError {
+    Unterminated,
+    BadEscape(char),
+    BadUnicode,
+}
+
+impl StringError {
+    // Message for a string starting at pos
+    pub(crate) fn at(&self, pos: Position) -> String {
+        let (line, column) = (pos.line, pos.column);
+        match self {
+            StringError::Unterminated => format!("Unterminated string at {}:{}", line, column),
+            StringError::BadEscape(c) => format!("Bad escape \\{} at {}:{}", c, line, column),
+            StringError::BadUnicode => {
+                format!("Bad unicode escape at {}:{}, expected \\u{{HEX}}", line, column)
+            }
+        }
+    }
+}
+
+// The value of a quoted string with its escapes decoded. A bad escape
+// doesn't stop scanning, the string is still consumed up to its quote.
+pub(crate) fn decoded_string<C: CharCursor>(s: &mut C, q: char) -> Option<Result<String, StringError>> {
+    s.accept_char(&[q])?;
+    let mut value = String::new();
+    let mut error = None;
+    loop {
+        match s.next_char() {
+            None => return Some(Err(StringError::Unterminated)),
+            Some(c) if c == q => return Some(error.map_or(Ok(value), Err)),
+            Some('\\') => match escape(s) {
+                Ok(c) => value.push(c),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            },
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+// Char for the escape after a backslash: \n \t \r \0 \\ \" \' or \u{1F600}
+fn escape<C: CharCursor>(s: &mut C) -> Result<char, StringError> {
+    match s.next_char() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some('0') => Ok('\0'),
+        Some(c @ ('\\' | '"' | '\'')) => Ok(c),
+        Some('u') => {
+            let mut hex = String::new();
+            if s.accept_char(&['{']).is_some() {
+                while let Some(h) = s.accept_char(HEXDIGITS) {
+                    hex.push(h);
+                }
+            }
+            let closed = s.accept_char(&['}']).is_some();
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .filter(|_| closed && hex.len() <= 6)
+                .and_then(char::from_u32)
+                .ok_or(StringError::BadUnicode)
+        }
+        Some(c) => Err(StringError::BadEscape(c)),
+        None => Err(StringError::Unterminated),
+    }
+}
+
+// Unicode identifiers, XID_Start or '_' followed by XID_Continue chars
+pub(crate) fn identifier<C: CharCursor>(s: &mut C) -> bool {
+    if s.accept_if(|c| c == '_' || c.is_xid_start()).is_none() {
+        return false;
+    }
+    while s.accept_if(UnicodeXID::is_xid_continue).is_some() {}
+    true
+}
 
 impl<I: Iterator<Item = char>> Scanner<I> {
     pub fn extract_string(&mut self) -> String {
         self.extract().into_iter().collect()
     }
 
+    pub fn extract_string_with_span(&mut self) -> (String, Span) {
+        let (chars, span) = self.extract_with_span();
+        (chars.into_iter().collect(), span)
+    }
+
     pub fn scan_whitespace(&mut self) -> Option<String> {
         self.skip_all(WHITE);
         Some(self.extract_string())
@@ -32,89 +283,40 @@ impl<I: Iterator<Item = char>> Scanner<I> {
 
     // scan numbers like -?[0-9]+(\.[0-9]+)?([eE][+-][0-9]+)?
     pub fn scan_number(&mut self) -> Option<String> {
-        let backtrack = self.buffer_pos();
-        // optional sign
-        self.accept_any(&['+', '-']);
-        // require integer part
-        if !self.skip_all(DIGITS) {
-            self.set_buffer_pos(backtrack);
-            return None;
-        }
-        // check for fractional part, else it's just an integer
-        let backtrack = self.buffer_pos();
-        if self.accept(&'.').is_some() && !self.skip_all(DIGITS) {
-            self.set_buffer_pos(backtrack);
-            return Some(self.extract_string()); // integer
-        }
-        // check for exponent part
-        let backtrack = self.buffer_pos();
-        if self.accept_any(&['e', 'E']).is_some() {
-            self.accept_any(&['+', '-']); // exponent sign is optional
-            if !self.skip_all(DIGITS) {
-                self.set_buffer_pos(backtrack);
-                return Some(self.extract_string()); //float
-            }
-        }
-        self.accept(&'i'); // accept imaginary numbers
-        Some(self.extract_string())
+        self.scan_number_with(NumberStyle::default())
+    }
+
+    // scan_number also accepting what style allows
+    pub fn scan_number_with(&mut self, style: NumberStyle) -> Option<String> {
+        number(self, style).then(|| self.extract_string())
     }
 
     pub fn scan_math_op(&mut self) -> Option<String> {
-        const OPS: &[char] = &['+', '-', '*', '/', '%', '^', '!', '(', ')', ','];
-        if self.accept_any(&['>', '=', '<']).is_some() {
-            // accept '<', '>', '=', '<=', '>=', '=='
-            self.accept(&'=');
-            Some(self.extract_string())
-        } else if self.accept(&'*').is_some() {
-            // accept '*', '**'
-            self.accept(&'*');
-            Some(self.extract_string())
-        } else if self.accept_any(OPS).is_some() {
-            Some(self.extract_string())
-        } else {
-            None
-        }
+        math_op(self).then(|| self.extract_string())
     }
 
     // scan integers like 0x34 0b10101 0o657
     pub fn scan_integer(&mut self) -> Option<String> {
-        let backtrack = self.buffer_pos();
-        if self.accept(&'0').is_some()
-            && match self.accept_any(&['x', 'o', 'b']) {
-                Some('x') => self.skip_all(HEXDIGITS),
-                Some('o') => self.skip_all(&HEXDIGITS[..8]),
-                Some('b') => self.skip_all(&HEXDIGITS[..2]),
-                _ => false,
-            }
-        {
-            return Some(self.extract_string());
-        }
-        self.set_buffer_pos(backtrack);
-        None
+        integer(self).then(|| self.extract_string())
     }
 
     // scan a quoted string like "this is \"an\" example"
     pub fn scan_quoted_string(&mut self, q: char) -> Option<String> {
-        let backtrack = self.buffer_pos();
-        self.accept(&q)?;
-        while let Some(n) = self.next() {
-            if n == '\\' {
-                self.next();
-                continue;
-            }
-            if n == q {
-                return Some(self.extract_string());
-            }
-        }
-        self.set_buffer_pos(backtrack);
-        None
+        quoted_string(self, q).then(|| self.extract_string())
+    }
+
+    // scan a quoted string returning its value with escapes decoded,
+    // a bad escape or a missing closing quote is an error
+    pub fn scan_decoded_string(&mut self, q: char) -> Option<Result<String, String>> {
+        let start = Scanner::position(self);
+        let value = decoded_string(self, q)?;
+        self.extract();
+        Some(value.map_err(|e| e.at(start)))
     }
 
-    // scan [a-zA-Z_][a-zA-Z0-9_]+
+    // scan identifiers like café, Δt or _x1 (XID_Start XID_Continue*)
     pub fn scan_identifier(&mut self) -> Option<String> {
-        self.accept_any(ALPHA)?;
-        self.skip_all(ALNUM);
-        Some(self.extract_string())
+        identifier(self).then(|| self.extract_string())
     }
 }
 
@@ -173,11 +375,48 @@ mod tests {
 
     #[test]
     fn scan_identifiers() {
-        let tests = vec!["id1", "func", "anyword", "_00", "bla23"];
+        let tests = vec!["id1", "func", "anyword", "_00", "bla23", "π", "café", "Δt", "変数"];
         for t in tests.iter() {
             let result = Scanner::new(t.chars()).scan_identifier();
             assert_eq!(Some(t.to_string()), result);
         }
+        assert_eq!(Scanner::new("1x".chars()).scan_identifier(), None);
+        assert_eq!(Scanner::new("x·y+".chars()).scan_identifier(), Some("x·y".to_string()));
+    }
+
+    #[test]
+    fn scan_decoded_string() {
+        let decode = |input: &str| Scanner::new(input.chars()).scan_decoded_string('"');
+        assert_eq!(decode(r#""a\tb\n \"c\" \\ \u{e9}\u{1F600}""#),
+                   Some(Ok("a\tb\n \"c\" \\ é😀".to_string())));
+        assert_eq!(decode("'a'"), None);
+        assert_eq!(decode(r#""\q""#), Some(Err("Bad escape \\q at 1:1".to_string())));
+        assert_eq!(decode(r#""\u{110000}""#),
+                   Some(Err("Bad unicode escape at 1:1, expected \\u{HEX}".to_string())));
+        assert_eq!(decode(r#""\u{41""#),
+                   Some(Err("Bad unicode escape at 1:1, expected \\u{HEX}".to_string())));
+        assert_eq!(decode("\"abc"), Some(Err("Unterminated string at 1:1".to_string())));
+        // errors consume the string so scanning can go on after it
+        let mut s = Scanner::new("x \"\\x\" y".chars());
+        s.scan_identifier();
+        s.scan_whitespace();
+        assert!(s.scan_decoded_string('"').unwrap().is_err());
+        s.scan_whitespace();
+        assert_eq!(s.scan_identifier(), Some("y".to_string()));
+    }
+
+    #[test]
+    fn scan_number_styles() {
+        let scan = |input: &str, style| Scanner::new(input.chars()).scan_number_with(style);
+        assert_eq!(scan("1_000_000", NumberStyle::default()), Some("1".to_string()));
+        let separators = NumberStyle { separators: true, ..NumberStyle::default() };
+        assert_eq!(scan("1_000.2_5e1_0", separators), Some("1_000.2_5e1_0".to_string()));
+        assert_eq!(scan("1__0", separators), Some("1".to_string()));
+        assert_eq!(scan("12_ ", separators), Some("12".to_string()));
+        assert_eq!(scan("_1", separators), None);
+        assert_eq!(scan("１２.５", separators), None);
+        let full_width = scan("-１２_３.５", NumberStyle::all()).unwrap();
+        assert_eq!(NumberStyle::normalize(&full_width), "-123.5");
     }
 
     #[test]
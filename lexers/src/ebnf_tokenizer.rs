@@ -1,66 +1,154 @@
 #![deny(warnings)]
 
 use crate::scanner::Scanner;
+use crate::trivia::Comments;
 
 pub struct EbnfTokenizer<I: Iterator<Item = char>> {
     input: Scanner<I>,
-    lookahead: Vec<String>,
+    lookahead: Vec<(String, (usize, usize))>,
+    // line/column where the last returned token starts
+    position: (usize, usize),
+    comments: Comments,
+    // why tokenizing stopped, like an unterminated comment
+    error: Option<String>,
 }
 
 impl<I: Iterator<Item = char>> EbnfTokenizer<I> {
     pub fn new(source: I) -> Self {
+        Self::with_comments(source, Comments::new().line("#"))
+    }
+
+    // Tokenizer skipping other comments than the default '#' line comments
+    pub fn with_comments(source: I, comments: Comments) -> Self {
         EbnfTokenizer {
             input: Scanner::new(source),
             lookahead: Vec::new(),
+            position: (1, 1),
+            comments,
+            error: None,
         }
     }
 
     pub fn scanner(source: I) -> Scanner<Self> {
         Scanner::new(Self::new(source))
     }
+
+    // Line and column (1-based) where the last token returned starts.
+    // Once the tokenizer returns None it's where tokenizing stopped.
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    // Why the tokenizer stopped early when it was something it could
+    // tell, like an unterminated block comment
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    // Check if all input was tokenized. The tokenizer stops early
+    // (returns None) when it finds something it can't understand.
+    pub fn exhausted(&mut self) -> bool {
+        self.lookahead.is_empty() && self.input.peek().is_none()
+    }
+
+    // Extract the scanner's buffer returning where it started
+    fn extract(&mut self) -> (String, (usize, usize)) {
+        let (text, span) = self.input.extract_string_with_span();
+        (text, (span.start.line, span.start.column))
+    }
+
+    fn token(&mut self) -> Option<String> {
+        let (token, start) = self.extract();
+        self.position = start;
+        Some(token)
+    }
+
+    // Scan a /regex/ or a character class like [a-z] as a single token.
+    // Classes can't have whitespace or unescaped quotes and need a range,
+    // negation or escape, otherwise '[' starts an optional group.
+    fn scan_pattern(&mut self) -> Option<String> {
+        let s = &mut self.input;
+        let backtrack = s.buffer_pos();
+        let close = match s.accept_any(&['/', '[']) {
+            Some('/') => '/',
+            Some(_) => ']',
+            None => return None,
+        };
+        let mut content = String::new();
+        while let Some(c) = s.next() {
+            if c == close && (close == '/' || !content.is_empty()) {
+                let class = content.starts_with('^') ||
+                    content.contains('-') || content.contains('\\');
+                if !content.is_empty() && (close == '/' || class) {
+                    return self.token();
+                }
+                break;
+            }
+            if c == '\n' || close == ']' &&
+                    (c.is_whitespace() || c == '\'' || c == '"') {
+                break;
+            }
+            content.push(c);
+            if c == '\\' {
+                match s.next() {
+                    Some(e) if e != '\n' => content.push(e),
+                    _ => break,
+                }
+            }
+        }
+        s.set_buffer_pos(backtrack);
+        None
+    }
 }
 
 impl<I: Iterator<Item = char>> Iterator for EbnfTokenizer<I> {
     type Item = String;
     fn next(&mut self) -> Option<Self::Item> {
         // used for accumulating string parts
-        if !self.lookahead.is_empty() {
-            return self.lookahead.pop();
+        if let Some((token, position)) = self.lookahead.pop() {
+            self.position = position;
+            return Some(token);
         }
-        let s = &mut self.input;
-        s.scan_whitespace();
-        // discard comments starting with '#' until new-line
-        if s.accept(&'#').is_some() {
-            while let Some(nl) = s.next() {
-                if nl == '\n' {
-                    s.extract(); // ignore comment
-                                 // discard comment and allow more by restarting
-                    return self.next();
-                }
-            }
+        // discard whitespace and comments, stop at unterminated ones
+        let trivia = self.input.scan_trivia(&self.comments);
+        let start = self.input.position();
+        self.position = (start.line, start.column);
+        if let Err(error) = trivia {
+            self.error = Some(error);
+            return None;
         }
-        if s.accept_any(&['[', ']', '{', '}', '(', ')', '|', ';'])
+        if let Some(pattern) = self.scan_pattern() {
+            return Some(pattern);
+        }
+        let s = &mut self.input;
+        if s.accept_any(&['[', ']', '{', '}', '(', ')', '|', ';',
+                          '?', '*', '+', ','])
             .is_some()
         {
-            return Some(s.extract_string());
+            return self.token();
         }
-        let backtrack = s.buffer_pos();
+        // repetition counts as in digit{2,4}
+        if s.skip_all(&['0', '1', '2', '3', '4', '5', '6', '7', '8', '9']) {
+            return self.token();
+        }
+        // ':=' or ':' for field labels
         if s.accept(&':').is_some() {
-            if s.accept(&'=').is_some() {
-                return Some(s.extract_string());
-            }
-            s.set_buffer_pos(backtrack);
+            s.accept(&'=');
+            return self.token();
         }
         let backtrack = s.buffer_pos();
         if let Some(q) = s.accept_any(&['"', '\'']) {
-            while let Some(n) = s.next() {
+            for n in s.by_ref() {
                 if n == q {
+                    let (v, (line, col)) = self.extract();
                     // store closing quote
-                    self.lookahead.push(n.to_string());
+                    let end = self.input.position();
+                    let close = (end.line, end.column - 1);
+                    self.lookahead.push((n.to_string(), close));
                     // store string content
-                    let v = s.extract_string();
-                    self.lookahead.push(v[1..v.len() - 1].to_string());
+                    self.lookahead.push((v[1..v.len() - 1].to_string(), (line, col + 1)));
                     // return opening quote
+                    self.position = (line, col);
                     return Some(q.to_string());
                 }
             }
@@ -77,3 +165,80 @@ impl<I: Iterator<Item = char>> Iterator for EbnfTokenizer<I> {
         None
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::EbnfTokenizer;
+    use crate::Comments;
+
+    #[test]
+    fn ebnf_tokenizer() {
+        let input = "# comment\nexpr := expr '+' num\n  | num @x ;";
+        let mut tok = EbnfTokenizer::new(input.chars());
+        let expected = vec![
+            ("expr", (2, 1)), (":=", (2, 6)), ("expr", (2, 9)),
+            ("'", (2, 14)), ("+", (2, 15)), ("'", (2, 16)), ("num", (2, 18)),
+            ("|", (3, 3)), ("num", (3, 5)), ("@x", (3, 9)), (";", (3, 12)),
+        ];
+        for (token, position) in expected {
+            assert_eq!(tok.next(), Some(token.to_string()));
+            assert_eq!(tok.position(), position);
+        }
+        assert_eq!(tok.next(), None);
+        assert!(tok.exhausted());
+    }
+
+    #[test]
+    fn ebnf_tokenizer_postfix() {
+        let input = "a := b? c* (d e)+ x:f{2,10} ;";
+        let tok = EbnfTokenizer::new(input.chars());
+        assert_eq!(tok.collect::<Vec<_>>(), vec![
+            "a", ":=", "b", "?", "c", "*", "(", "d", "e", ")", "+",
+            "x", ":", "f", "{", "2", ",", "10", "}", ";",
+        ].into_iter().map(|t| t.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ebnf_tokenizer_patterns() {
+        let input = r#"n := /[0-9]+(\.\/)?/ [a-z] [^\"] [x] [ y ] '/' ;"#;
+        let tok = EbnfTokenizer::new(input.chars());
+        assert_eq!(tok.collect::<Vec<_>>(), vec![
+            "n", ":=", r#"/[0-9]+(\.\/)?/"#, "[a-z]", r#"[^\"]"#,
+            "[", "x", "]", "[", "y", "]", "'", "/", "'", ";",
+        ].into_iter().map(|t| t.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ebnf_tokenizer_stops() {
+        let mut tok = EbnfTokenizer::new("a := b\n  $ c ;".chars());
+        assert_eq!(tok.by_ref().count(), 3);
+        assert_eq!(tok.position(), (2, 3));
+        assert_eq!(tok.error(), None);
+        assert!(!tok.exhausted());
+        // unterminated strings can't be tokenized either
+        let mut tok = EbnfTokenizer::new("a := 'b ;".chars());
+        assert_eq!(tok.by_ref().count(), 2);
+        assert_eq!(tok.position(), (1, 6));
+        assert!(!tok.exhausted());
+    }
+
+    #[test]
+    fn ebnf_tokenizer_comments() {
+        let comments = Comments::new().line("//").block("(*", "*)");
+        let input = "// rules\na := (* (* nested *) *) b ; // c";
+        let mut tok = EbnfTokenizer::with_comments(input.chars(), comments.clone());
+        assert_eq!(tok.by_ref().collect::<Vec<_>>(), vec!["a", ":=", "b", ";"]);
+        assert!(tok.exhausted());
+        // custom comments replace the default #
+        let mut tok = EbnfTokenizer::with_comments("a # c".chars(), comments.clone());
+        assert_eq!(tok.by_ref().count(), 1);
+        assert!(!tok.exhausted());
+        let mut tok = EbnfTokenizer::with_comments("a (* b".chars(), comments);
+        assert_eq!(tok.by_ref().count(), 1);
+        assert_eq!(tok.position(), (1, 3));
+        assert_eq!(tok.error(), Some("Unterminated comment at 1:3"));
+        assert!(!tok.exhausted());
+    }
+}
@@ -0,0 +1,214 @@
+#![deny(warnings)]
+
+// Block structure from indentation, Python style. Tokens of each line come
+// from a scan function, the adapter adds Newline at the end of lines with
+// tokens and Indent/Dedent when indentation changes. Blank lines and lines
+// with only comments don't count. Tokenizers that skip newlines, like
+// MathTokenizer, can't tell lines apart so their scan functions are used.
+
+use crate::scanner::Scanner;
+use crate::span::Position;
+use crate::trivia::Comments;
+use std::collections::VecDeque;
+
+/// A scanned token or one added for the line structure, at the Position
+/// where the line ends (Newline) or its first token starts (Indent/Dedent)
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndentToken<T> {
+    Token(T),
+    Newline(Position),
+    Indent(Position),
+    Dedent(Position),
+}
+
+/// Tokens scanned by a function over the Scanner of a char source, like
+/// `|s| s.scan_identifier()`, with Newline, Indent and Dedent tokens. The
+/// function must not consume new lines. Indentation can use tabs or spaces,
+/// but a deeper level has to start with the indentation of the level it's in.
+pub struct Indented<I: Iterator<Item = char>, T, F> {
+    src: Scanner<I>,
+    comments: Comments,
+    scan: F,
+    // indentation of the open blocks, the outermost is ""
+    levels: Vec<String>,
+    pending: VecDeque<IndentToken<T>>,
+    line_start: bool,
+    done: bool,
+}
+
+impl<I, T, F> Indented<I, T, F>
+where
+    I: Iterator<Item = char>,
+    F: FnMut(&mut Scanner<I>) -> Option<T>,
+{
+    pub fn new(source: I, comments: Comments, scan: F) -> Self {
+        Indented {
+            src: Scanner::new(source),
+            comments,
+            scan,
+            levels: vec![String::new()],
+            pending: VecDeque::new(),
+            line_start: true,
+            done: false,
+        }
+    }
+
+    // Skip spaces and comments within a line
+    fn skip_inline(&mut self) -> Result<(), String> {
+        while self.src.skip_all(&[' ', '\t', '\r']) || self.src.comment(&self.comments)?.is_some() {}
+        self.src.extract();
+        Ok(())
+    }
+
+    // Open or close blocks for the indentation of a line
+    fn indent(&mut self, indent: String, at: Position) -> Result<(), String> {
+        let top = self.levels.last().map_or("", String::as_str);
+        if indent == top {
+            return Ok(());
+        }
+        if indent.starts_with(top) {
+            self.levels.push(indent);
+            self.pending.push_back(IndentToken::Indent(at));
+            return Ok(());
+        }
+        if !top.starts_with(indent.as_str()) {
+            return Err(format!("Inconsistent tabs and spaces at {}:{}", at.line, at.column));
+        }
+        if !self.levels.contains(&indent) {
+            return Err(format!("Dedent doesn't match any outer block at {}:{}",
+                               at.line, at.column));
+        }
+        while self.levels.last() != Some(&indent) {
+            self.levels.pop();
+            self.pending.push_back(IndentToken::Dedent(at));
+        }
+        Ok(())
+    }
+
+    fn token(&mut self) -> Result<Option<IndentToken<T>>, String> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Ok(Some(token));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            if self.line_start {
+                self.src.skip_all(&[' ', '\t']);
+                let (indent, span) = self.src.extract_string_with_span();
+                self.skip_inline()?;
+                match self.src.peek() {
+                    // blank line
+                    Some('\n') => {
+                        self.src.next();
+                        self.src.extract();
+                    }
+                    // close all blocks at the end
+                    None => {
+                        self.done = true;
+                        let blocks = self.levels.len() - 1;
+                        let at = self.src.position();
+                        self.pending.extend((0..blocks).map(|_| IndentToken::Dedent(at)));
+                    }
+                    Some(_) => {
+                        self.line_start = false;
+                        self.indent(indent, span.end)?;
+                    }
+                }
+                continue;
+            }
+            self.skip_inline()?;
+            match self.src.peek() {
+                Some('\n') | None => {
+                    let at = self.src.position();
+                    self.src.next();
+                    self.src.extract();
+                    self.line_start = true;
+                    return Ok(Some(IndentToken::Newline(at)));
+                }
+                Some(_) => {
+                    let start = self.src.position();
+                    return match (self.scan)(&mut self.src) {
+                        Some(token) => Ok(Some(IndentToken::Token(token))),
+                        None => Err(format!("No token at {}:{}", start.line, start.column)),
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl<I, T, F> Iterator for Indented<I, T, F>
+where
+    I: Iterator<Item = char>,
+    F: FnMut(&mut Scanner<I>) -> Option<T>,
+{
+    type Item = Result<IndentToken<T>, String>;
+    // Iteration stops after an error
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.token();
+        if token.is_err() {
+            self.done = true;
+            self.pending.clear();
+        }
+        token.transpose()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::{IndentToken::*, Indented};
+    use crate::{Comments, Position};
+
+    fn tokens(input: &str) -> Result<Vec<String>, String> {
+        let comments = Comments::new().line("#");
+        Indented::new(input.chars(), comments, |s| s.scan_identifier().or_else(|| s.scan_math_op()))
+            .map(|t| t.map(|t| match t {
+                Token(t) => t,
+                Newline(_) => "NL".to_string(),
+                Indent(_) => "IN".to_string(),
+                Dedent(_) => "DE".to_string(),
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn blocks() {
+        let input = "a\n  b # c\n\n    # d\n    e\n\n  f\nx(y)";
+        assert_eq!(tokens(input).unwrap().join(" "),
+                   "a NL IN b NL IN e NL DE f NL DE x ( y ) NL");
+        assert_eq!(tokens("a\n\tb\n\t\tc\n").unwrap().join(" "),
+                   "a NL IN b NL IN c NL DE DE");
+        assert_eq!(tokens("\n  # only comments\n").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn positions() {
+        let at = |offset, line, column| Position { offset, line, column };
+        let tokens: Vec<_> = Indented::new("a\n  b\nc".chars(), Comments::new(), |s| s.scan_identifier())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokens, vec![
+            Token("a".to_string()), Newline(at(1, 1, 2)),
+            Indent(at(4, 2, 3)), Token("b".to_string()), Newline(at(5, 2, 4)),
+            Dedent(at(6, 3, 1)), Token("c".to_string()), Newline(at(7, 3, 2)),
+        ]);
+        let tokens: Vec<_> = Indented::new("a\n  b\n".chars(), Comments::new(), |s| s.scan_identifier())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokens.last(), Some(&Dedent(at(6, 3, 1))));
+    }
+
+    #[test]
+    fn indentation_errors() {
+        assert_eq!(tokens("a\n    b\n  c"),
+                   Err("Dedent doesn't match any outer block at 3:3".to_string()));
+        assert_eq!(tokens("a\n\tb\n  c"),
+                   Err("Inconsistent tabs and spaces at 3:3".to_string()));
+        assert_eq!(tokens("a\n  \tb\n  \t c\n   \tc"),
+                   Err("Inconsistent tabs and spaces at 4:5".to_string()));
+        assert_eq!(tokens("a $"), Err("No token at 1:3".to_string()));
+    }
+}
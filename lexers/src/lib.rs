@@ -2,8 +2,30 @@
 
 mod char_scanner;
 mod scanner;
+pub use crate::char_scanner::NumberStyle;
 pub use crate::scanner::Scanner;
 
+mod str_scanner;
+pub use crate::str_scanner::StrScanner;
+
+mod read_chars;
+pub use crate::read_chars::ReadChars;
+
+mod span;
+pub use crate::span::{Advance, Position, Span};
+
+mod trivia;
+pub use crate::trivia::{Comments, Lossless, Trivia, TriviaKind, TriviaToken};
+
+mod indent;
+pub use crate::indent::{IndentToken, Indented};
+
+mod regex;
+pub use crate::regex::Regex;
+
+mod tokenizer_builder;
+pub use crate::tokenizer_builder::{Tokenizer, TokenizerBuilder, Tokens};
+
 mod ebnf_tokenizer;
 pub use crate::ebnf_tokenizer::EbnfTokenizer;
 
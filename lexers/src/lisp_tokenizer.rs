@@ -1,6 +1,9 @@
 #![deny(warnings)]
 
+use crate::char_scanner::NumberStyle;
 use crate::scanner::Scanner;
+use crate::span::Advance;
+use crate::str_scanner::StrScanner;
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum LispToken {
@@ -17,33 +20,69 @@ pub enum LispToken {
     String(String),
 }
 
-pub struct LispTokenizer<I: Iterator<Item = char>>(Scanner<I>);
+impl Advance for LispToken {}
+
+// Plain number for lexemes like 1_000 or １２, anything else as is
+fn number(lexeme: &str) -> String {
+    let mut s = StrScanner::new(lexeme);
+    match s.scan_number_with(NumberStyle::all()) {
+        Some(num) if s.peek().is_none() => NumberStyle::normalize(num),
+        _ => lexeme.to_string(),
+    }
+}
+
+/// Tokens of lisp source. They stop before a string with a bad escape or
+/// no closing quote, see `error`.
+pub struct LispTokenizer<I: Iterator<Item = char>> {
+    src: Scanner<I>,
+    error: Option<String>,
+}
 
 impl<I: Iterator<Item = char>> LispTokenizer<I> {
     pub fn new(source: I) -> Self {
-        LispTokenizer(Scanner::new(source))
+        LispTokenizer { src: Scanner::new(source), error: None }
     }
 
     pub fn scanner(source: I) -> Scanner<Self> {
         Scanner::new(Self::new(source))
     }
+
+    /// Why the tokens stopped before the end of the input
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl<I: Iterator<Item = char>> Scanner<LispTokenizer<I>> {
+    /// The bad string that ended the tokens early, if any
+    pub fn token_error(&self) -> Option<&str> {
+        self.source().error()
+    }
 }
 
 impl<I: Iterator<Item = char>> Iterator for LispTokenizer<I> {
     type Item = LispToken;
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.scan_whitespace();
-        if let Some(s) = self.0.scan_quoted_string('"') {
-            return Some(LispToken::String(s));
+        if self.error.is_some() {
+            return None;
         }
-        if let Some(lexeme) = self.0.accept_any(&[')', '(', '\'', '`', ',']) {
+        self.src.scan_whitespace();
+        match self.src.scan_decoded_string('"') {
+            Some(Ok(s)) => return Some(LispToken::String(s)),
+            Some(Err(e)) => {
+                self.error = Some(e);
+                return None;
+            }
+            None => (),
+        }
+        if let Some(lexeme) = self.src.accept_any(&[')', '(', '\'', '`', ',']) {
             let token = match lexeme {
                 '(' => LispToken::OParen,
                 ')' => LispToken::CParen,
                 '\'' => LispToken::Quote,
                 '`' => LispToken::QuasiQuote,
                 ',' => {
-                    if self.0.accept(&'@').is_some() {
+                    if self.src.accept(&'@').is_some() {
                         LispToken::UnQSplice
                     } else {
                         LispToken::UnQuote
@@ -51,16 +90,16 @@ impl<I: Iterator<Item = char>> Iterator for LispTokenizer<I> {
                 }
                 _ => unreachable!(),
             };
-            self.0.extract(); // ignore
+            self.src.extract(); // ignore
             return Some(token);
         }
-        if self.0.until_any(&[')', ' ', '\n', '\r', '\t']) {
+        if self.src.until_any(&[')', ' ', '\n', '\r', '\t']) {
             use std::str::FromStr;
-            let lexeme = self.0.extract_string();
+            let lexeme = self.src.extract_string();
             return match &lexeme[..] {
                 "#t" => Some(LispToken::True),
                 "#f" => Some(LispToken::False),
-                num => match f64::from_str(num) {
+                num => match f64::from_str(&number(num)) {
                     Ok(n) => Some(LispToken::Number(n)),
                     _ => Some(LispToken::Symbol(lexeme)),
                 },
@@ -79,7 +118,7 @@ mod tests {
     #[test]
     fn lisp_tokenizer() {
         use LispToken::*;
-        let inputs = vec!["(+ 3 4 5)", "(max 'a \"hello\")"];
+        let inputs = vec!["(+ 3 4 5)", "(max 'a \"hello\")", "(λ 1_000 x_1 ３)", r#""a\"\n" "\x""#];
         let expect = vec![
             vec![
                 OParen,
@@ -94,9 +133,18 @@ mod tests {
                 Symbol(format!("max")),
                 Quote,
                 Symbol(format!("a")),
-                String(format!("\"hello\"")),
+                String(format!("hello")),
+                CParen,
+            ],
+            vec![
+                OParen,
+                Symbol(format!("λ")),
+                Number(1000.0),
+                Symbol(format!("x_1")),
+                Number(3.0),
                 CParen,
             ],
+            vec![String(format!("a\"\n"))],
         ];
         for (input, expected) in inputs.iter().zip(expect.iter()) {
             let mut lx = LispTokenizer::new(input.chars());
@@ -106,4 +154,15 @@ mod tests {
             assert_eq!(lx.next(), None);
         }
     }
+
+    #[test]
+    fn bad_strings() {
+        let mut lx = LispTokenizer::scanner(r#"(print "a\x" b)"#.chars());
+        assert_eq!(lx.next(), Some(LispToken::OParen));
+        assert_eq!(lx.next(), Some(LispToken::Symbol("print".to_string())));
+        assert_eq!(lx.token_error(), None);
+        assert_eq!(lx.next(), None);
+        assert_eq!(lx.token_error(), Some("Bad escape \\x at 1:8"));
+        assert_eq!(lx.next(), None);
+    }
 }
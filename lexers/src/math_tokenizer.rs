@@ -1,6 +1,8 @@
 #![deny(warnings)]
 
+use crate::char_scanner::NumberStyle;
 use crate::scanner::Scanner;
+use crate::span::Advance;
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum MathToken {
@@ -15,6 +17,8 @@ pub enum MathToken {
     Comma,
 }
 
+impl Advance for MathToken {}
+
 pub struct MathTokenizer<I: Iterator<Item = char>> {
     src: Scanner<I>,
     prev: Option<MathToken>,
@@ -60,8 +64,9 @@ impl<I: Iterator<Item = char>> MathTokenizer<I> {
                 _ => Some(MathToken::Variable(id)),
             };
         }
-        if let Some(num) = self.src.scan_number() {
+        if let Some(num) = self.src.scan_number_with(NumberStyle::all()) {
             use std::str::FromStr;
+            let num = NumberStyle::normalize(&num);
             return Some(MathToken::Number(f64::from_str(&num).unwrap()));
         }
         if self.src.next().is_some() {
@@ -147,6 +152,23 @@ mod tests {
         assert_eq!(lx.next(), None);
     }
 
+    #[test]
+    fn unicode_and_separators() {
+        let mut lx = MathTokenizer::new("1_000 * Δt + ２".chars());
+        let expect = [
+            Number(1000.0),
+            BOp(format!("*")),
+            Variable(format!("Δt")),
+            BOp(format!("+")),
+            Number(2.0),
+        ];
+        for exp_token in expect.iter() {
+            let token = lx.next().unwrap();
+            assert_eq!(*exp_token, token);
+        }
+        assert_eq!(lx.next(), None);
+    }
+
     #[test]
     fn unary_ops() {
         let mut lx = MathTokenizer::new("x---y".chars());
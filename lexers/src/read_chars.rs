@@ -0,0 +1,161 @@
+#![deny(warnings)]
+
+// Chars decoded from a byte stream as the scanner asks for them, so input
+// like a log stream or stdin doesn't need to be read into a String first.
+// The scanner hands over its buffer on extract, so memory is only held for
+// the token being scanned and the reader's own buffer.
+
+use crate::scanner::Scanner;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Iterator over the chars of a UTF-8 stream. It stops at the end of the
+/// stream or at the first I/O or encoding error, see `error`.
+pub struct ReadChars<R: BufRead> {
+    reader: R,
+    // bytes decoded so far
+    offset: usize,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> ReadChars<R> {
+    pub fn new(reader: R) -> Self {
+        ReadChars { reader, offset: 0, error: None }
+    }
+
+    /// Why the chars stopped before the end of the stream
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    fn fail(&mut self, error: io::Error) -> Option<char> {
+        self.error = Some(error);
+        None
+    }
+
+    fn invalid(&mut self, start: usize) -> Option<char> {
+        let message = format!("Invalid UTF-8 at byte {}", start);
+        self.fail(io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+}
+
+impl<R: BufRead> Iterator for ReadChars<R> {
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        if self.error.is_some() {
+            return None;
+        }
+        let start = self.offset;
+        let mut bytes = [0u8; 4];
+        let mut len = 0;
+        let mut width = 1;
+        while len < width {
+            let byte = match self.reader.fill_buf() {
+                Ok(buf) => buf.first().copied(),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return self.fail(e),
+            };
+            let byte = match byte {
+                Some(byte) => byte,
+                None if len == 0 => return None,
+                None => return self.invalid(start),
+            };
+            if len == 0 {
+                width = match byte {
+                    0x00..=0x7f => 1,
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf4 => 4,
+                    _ => return self.invalid(start),
+                };
+            } else if byte & 0xc0 != 0x80 {
+                // leave the byte, it may start the next char
+                return self.invalid(start);
+            }
+            bytes[len] = byte;
+            len += 1;
+            self.reader.consume(1);
+            self.offset += 1;
+        }
+        match std::str::from_utf8(&bytes[..len]) {
+            Ok(c) => c.chars().next(),
+            Err(_) => self.invalid(start),
+        }
+    }
+}
+
+impl<R: BufRead> Scanner<ReadChars<R>> {
+    /// Scanner decoding chars from a buffered reader as it needs them
+    pub fn from_buf_read(reader: R) -> Self {
+        Scanner::new(ReadChars::new(reader))
+    }
+
+    /// The I/O or encoding error that ended the input early, if any
+    pub fn read_error(&self) -> Option<&io::Error> {
+        self.source().error()
+    }
+}
+
+impl<R: Read> Scanner<ReadChars<BufReader<R>>> {
+    /// Scanner decoding chars from a reader as it needs them
+    pub fn from_read(reader: R) -> Self {
+        Scanner::from_buf_read(BufReader::new(reader))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::ReadChars;
+    use crate::Scanner;
+    use std::io::{self, BufReader, Read};
+
+    #[test]
+    fn utf8_across_reads() {
+        // a one byte buffer splits every multi-byte char across reads
+        let input = "día ∑ 😀!";
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        assert_eq!(ReadChars::new(reader).collect::<String>(), input);
+    }
+
+    #[test]
+    fn encoding_errors() {
+        let mut chars = ReadChars::new(&b"ab\xe2\x88x"[..]);
+        assert_eq!(chars.by_ref().collect::<String>(), "ab");
+        let error = chars.error().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Invalid UTF-8 at byte 2");
+        assert_eq!(chars.next(), None);
+        // truncated at the end, overlong and surrogate encodings
+        for bytes in [&b"a\xf0\x9f"[..], b"\xc0\x80", b"\xed\xa0\x80"] {
+            let mut chars = ReadChars::new(bytes);
+            chars.by_ref().count();
+            assert!(chars.error().is_some());
+        }
+    }
+
+    #[test]
+    fn streaming() {
+        // an endless stream is only read as far as tokens are scanned
+        let stream = "let x1 = ".as_bytes().chain(io::repeat(b'7'));
+        let mut s = Scanner::from_read(stream);
+        assert_eq!(s.scan_identifier(), Some("let".to_string()));
+        s.scan_whitespace();
+        assert_eq!(s.scan_identifier(), Some("x1".to_string()));
+        s.scan_whitespace();
+        assert_eq!(s.scan_math_op(), Some("=".to_string()));
+        assert!(s.read_error().is_none());
+
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("pipe closed"))
+            }
+        }
+        let mut s = Scanner::from_read("a ".as_bytes().chain(Broken));
+        assert_eq!(s.scan_identifier(), Some("a".to_string()));
+        s.scan_whitespace();
+        assert_eq!(s.next(), None);
+        assert_eq!(s.read_error().map(|e| e.to_string()), Some("pipe closed".to_string()));
+    }
+}
@@ -0,0 +1,455 @@
+#![deny(warnings)]
+
+// A small regular expression engine (Thompson NFA simulation).
+// Supports: literals, '.', escapes (\d \w \s \D \W \S \n \t \r, \xHH and
+// \x{HHHH} code points, and quoting),
+// classes [a-z] [^"\\], groups (..), alternation '|', quantifiers * + ? {n,m}.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Matcher {
+    Any,
+    Char(char),
+    Class(bool, Vec<(char, char)>), // (negated, ranges)
+}
+
+impl Matcher {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Matcher::Any => true,
+            Matcher::Char(x) => *x == c,
+            Matcher::Class(negated, ranges) => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Atom(Matcher),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Match,
+    Char(Matcher),
+    Split(usize, usize),
+    Jmp(usize),
+}
+
+// Max number of copies when expanding bounded repetition
+const MAX_REPEAT: usize = 1000;
+
+// Max number of instructions, nested repetitions multiply their copies
+const MAX_PROGRAM: usize = 100_000;
+
+#[derive(Clone, Debug)]
+pub struct Regex {
+    source: String,
+    program: Vec<Inst>,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unbalanced ')'"));
+        }
+        let mut program = Vec::new();
+        compile(&node, &mut program)?;
+        program.push(Inst::Match);
+        Ok(Regex {
+            source: pattern.to_string(),
+            program,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    // Check if the whole input matches the regex
+    pub fn is_match(&self, input: &str) -> bool {
+        self.match_len(input) == Some(input.len())
+    }
+
+    // Length in bytes of the longest prefix of input matching the regex
+    pub fn match_len(&self, input: &str) -> Option<usize> {
+        self.longest_match(input.char_indices().map(|(i, c)| (i + c.len_utf8(), c)))
+    }
+
+    // Longest match over (offset after char, char) pairs
+    fn longest_match<I: Iterator<Item = (usize, char)>>(&self, input: I) -> Option<usize> {
+        let mut current = Vec::new();
+        let mut seen = vec![false; self.program.len()];
+        self.add_thread(&mut current, &mut seen, 0);
+        let mut longest = None;
+        if current.iter().any(|&pc| matches!(self.program[pc], Inst::Match)) {
+            longest = Some(0);
+        }
+        for (end, c) in input {
+            let mut next = Vec::new();
+            let mut seen = vec![false; self.program.len()];
+            for &pc in &current {
+                if let Inst::Char(ref m) = self.program[pc] {
+                    if m.matches(c) {
+                        self.add_thread(&mut next, &mut seen, pc + 1);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            if next.iter().any(|&pc| matches!(self.program[pc], Inst::Match)) {
+                longest = Some(end);
+            }
+            current = next;
+        }
+        longest
+    }
+
+    // Follow epsilon transitions adding instructions that consume input
+    fn add_thread(&self, threads: &mut Vec<usize>, seen: &mut [bool], pc: usize) {
+        if seen[pc] {
+            return;
+        }
+        seen[pc] = true;
+        match self.program[pc] {
+            Inst::Jmp(to) => self.add_thread(threads, seen, to),
+            Inst::Split(a, b) => {
+                self.add_thread(threads, seen, a);
+                self.add_thread(threads, seen, b);
+            }
+            _ => threads.push(pc),
+        }
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) -> Result<(), String> {
+    if program.len() > MAX_PROGRAM {
+        return Err(format!("Regex error: more than {} instructions", MAX_PROGRAM));
+    }
+    match node {
+        Node::Empty => (),
+        Node::Atom(m) => program.push(Inst::Char(m.clone())),
+        Node::Concat(nodes) => nodes.iter().try_for_each(|n| compile(n, program))?,
+        Node::Alt(nodes) => {
+            // split L1, next; L1: n0; jmp end; next: split L2, ...
+            let mut jumps = Vec::new();
+            for (idx, n) in nodes.iter().enumerate() {
+                if idx + 1 < nodes.len() {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(n, program)?;
+                    jumps.push(program.len());
+                    program.push(Inst::Jmp(0));
+                    let next = program.len();
+                    program[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(n, program)?;
+                }
+            }
+            let end = program.len();
+            for j in jumps {
+                program[j] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat(n, min, max) => {
+            for _ in 0..*min {
+                compile(n, program)?;
+            }
+            match max {
+                None => {
+                    // L1: split L2, end; L2: n; jmp L1
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(n, program)?;
+                    program.push(Inst::Jmp(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    // nested optionals: (n(n(n)?)?)?
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(program.len() + 1, 0));
+                        compile(n, program)?;
+                    }
+                    let end = program.len();
+                    for s in splits {
+                        program[s] = Inst::Split(s + 1, end);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+struct RegexParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl RegexParser {
+    fn error(&self, msg: &str) -> String {
+        format!("Regex error at {}: {}", self.pos, msg)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn accept(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.concatenation()?];
+        while self.accept('|') {
+            alternatives.push(self.concatenation()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.swap_remove(0),
+            _ => Node::Alt(alternatives),
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.swap_remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let mut node = atom;
+        loop {
+            let (min, max) = if self.accept('*') {
+                (0, None)
+            } else if self.accept('+') {
+                (1, None)
+            } else if self.accept('?') {
+                (0, Some(1))
+            } else if self.peek() == Some('{') {
+                self.pos += 1;
+                let min = self.number().ok_or_else(|| self.error("expected a number"))?;
+                let max = if self.accept(',') {
+                    self.number()
+                } else {
+                    Some(min)
+                };
+                if !self.accept('}') {
+                    return Err(self.error("expected '}'"));
+                }
+                if max.is_some_and(|max| max < min) || min.max(max.unwrap_or(0)) > MAX_REPEAT {
+                    return Err(self.error("bad repetition bounds"));
+                }
+                (min, max)
+            } else {
+                return Ok(node);
+            };
+            node = Node::Repeat(Box::new(node), min, max);
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(match c {
+            '(' => {
+                let node = self.alternation()?;
+                if !self.accept(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                node
+            }
+            '[' => Node::Atom(self.class()?),
+            '.' => Node::Atom(Matcher::Any),
+            '\\' => Node::Atom(self.escape()?),
+            '*' | '+' | '?' | '{' => return Err(self.error("nothing to repeat")),
+            c => Node::Atom(Matcher::Char(c)),
+        })
+    }
+
+    fn escape(&mut self) -> Result<Matcher, String> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        let digit = vec![('0', '9')];
+        let word = vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+        let space = vec![('\t', '\r'), (' ', ' ')];
+        Ok(match c {
+            'd' => Matcher::Class(false, digit),
+            'D' => Matcher::Class(true, digit),
+            'w' => Matcher::Class(false, word),
+            'W' => Matcher::Class(true, word),
+            's' => Matcher::Class(false, space),
+            'S' => Matcher::Class(true, space),
+            'n' => Matcher::Char('\n'),
+            't' => Matcher::Char('\t'),
+            'r' => Matcher::Char('\r'),
+            'x' => Matcher::Char(self.hex()?),
+            c if c.is_alphanumeric() => return Err(self.error("unknown escape")),
+            c => Matcher::Char(c),
+        })
+    }
+
+    // Single char inside a class
+    fn class_char(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| self.error("expected ']'"))?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c);
+        }
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'x' => self.hex()?,
+            c => c,
+        })
+    }
+
+    // Code point after \x, either two hex digits or braced as in \x{1F600}
+    fn hex(&mut self) -> Result<char, String> {
+        let braced = self.accept('{');
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_hexdigit() || !braced && digits.len() == 2 {
+                break;
+            }
+            digits.push(c);
+            self.pos += 1;
+        }
+        if braced && !self.accept('}') || digits.is_empty() || digits.len() > 6 ||
+                !braced && digits.len() != 2 {
+            return Err(self.error("bad hex escape"));
+        }
+        u32::from_str_radix(&digits, 16).ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| self.error("bad code point"))
+    }
+
+    fn class(&mut self) -> Result<Matcher, String> {
+        let negated = self.accept('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        while first || self.peek() != Some(']') {
+            first = false;
+            if self.peek() == Some('\\') {
+                // class escapes like \d can be nested
+                if let Some('d') | Some('w') | Some('s') = self.chars.get(self.pos + 1) {
+                    self.pos += 1;
+                    if let Matcher::Class(_, r) = self.escape()? {
+                        ranges.extend(r);
+                    }
+                    continue;
+                }
+            }
+            let lo = self.class_char()?;
+            let hi = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                self.class_char()?
+            } else {
+                lo
+            };
+            if hi < lo {
+                return Err(self.error("bad class range"));
+            }
+            ranges.push((lo, hi));
+        }
+        self.pos += 1; // closing ']'
+        Ok(Matcher::Class(negated, ranges))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    #[test]
+    fn regex_match() {
+        let tests = vec![
+            ("[0-9]+(\\.[0-9]+)?", vec!["0", "42", "3.14"], vec!["", "3.", ".5", "a"]),
+            ("[a-z_][a-z0-9_]*", vec!["x", "_a1", "abc"], vec!["1a", "A", ""]),
+            ("ab|cd|", vec!["ab", "cd", ""], vec!["a", "abcd"]),
+            ("a{2,3}b?", vec!["aa", "aaa", "aab"], vec!["a", "aaaa", "b"]),
+            ("a{2}", vec!["aa"], vec!["a", "aaa"]),
+            ("a{2,}", vec!["aa", "aaaaa"], vec!["a"]),
+            ("[^\"\\\\]*", vec!["", "abc", "a b"], vec!["\"", "\\"]),
+            ("\\d+\\s\\w", vec!["12 a", "1\t_"], vec!["12a", "a a"]),
+            ("[-+]?\\d", vec!["-1", "+2", "3"], vec!["--1"]),
+            ("(a|b)*c", vec!["c", "abbac"], vec!["abba"]),
+            ("é.π", vec!["éxπ", "é π"], vec!["eeπ"]),
+            ("\\x41[\\x{0}-\\x1F]\\x{1F600}", vec!["A\0😀", "A\n😀"], vec!["A 😀"]),
+        ];
+        for (pattern, good, bad) in tests {
+            let re = Regex::new(pattern).unwrap();
+            for g in good {
+                assert!(re.is_match(g), "{} should match {:?}", pattern, g);
+            }
+            for b in bad {
+                assert!(!re.is_match(b), "{} shouldn't match {:?}", pattern, b);
+            }
+        }
+    }
+
+    #[test]
+    fn regex_prefix() {
+        let re = Regex::new("[0-9]+(\\.[0-9]+)?").unwrap();
+        assert_eq!(re.match_len("3.14 + 2"), Some(4));
+        assert_eq!(re.match_len("3. + 2"), Some(1));
+        assert_eq!(re.match_len("x"), None);
+        let re = Regex::new("a*").unwrap();
+        assert_eq!(re.match_len("b"), Some(0));
+    }
+
+    #[test]
+    fn regex_errors() {
+        for bad in ["(a", "a)", "*a", "[a-", "a{3,1}", "[z-a]", "\\q", "a{x}",
+                    "\\x4", "\\x{}", "\\x{41", "\\x{d800}"] {
+            assert!(Regex::new(bad).is_err(), "{} should fail", bad);
+        }
+        // each count is fine but nested they multiply
+        assert!(Regex::new("(a{1000}){50}").is_ok());
+        assert_eq!(Regex::new("((a{1000}){1000}){1000}").err(),
+                   Some("Regex error: more than 100000 instructions".to_string()));
+    }
+}
@@ -1,5 +1,7 @@
 #![deny(warnings)]
 
+use crate::span::{Advance, Position, Span};
+
 pub struct Scanner<I: Iterator>
 where
     I::Item: Clone,
@@ -7,6 +9,8 @@ where
     src: I,
     buf: Vec<I::Item>,
     pos: isize,
+    // where the buffer starts in the source
+    start: Position,
 }
 
 // Scanners are Iterators
@@ -39,9 +43,15 @@ where
             src: source,
             buf: Vec::new(),
             pos: -1,
+            start: Position::default(),
         }
     }
 
+    // The iterator the scanner reads from
+    pub(crate) fn source(&self) -> &I {
+        &self.src
+    }
+
     // Allows getting current buffer position to backtrack
     pub fn buffer_pos(&self) -> isize {
         self.pos
@@ -95,9 +105,20 @@ where
         let n = (self.pos + 1) as usize;
         &self.buf[..n]
     }
+}
 
+impl<I> Scanner<I>
+where
+    I: Iterator,
+    I::Item: Clone + Advance,
+{
     // Consumes the buffer into a new token (which can be ignored)
     pub fn extract(&mut self) -> Vec<I::Item> {
+        self.extract_with_span().0
+    }
+
+    // Like extract also returning where the token is in the source
+    pub fn extract_with_span(&mut self) -> (Vec<I::Item>, Span) {
         // Check where to shift buffer
         let split_point = std::cmp::min(self.pos + 1, self.buf.len() as isize);
         assert!(split_point >= 0);
@@ -106,7 +127,14 @@ where
         // Split buffer and keep the remainder
         let mut remaining = self.buf.split_off(split_point as usize);
         std::mem::swap(&mut self.buf, &mut remaining);
-        remaining
+        let start = self.start;
+        self.start = start.after(&remaining);
+        (remaining, Span { start, end: self.start })
+    }
+
+    // Position right after the current item, where the next one starts
+    pub fn position(&self) -> Position {
+        self.start.after(self.view())
     }
 }
 
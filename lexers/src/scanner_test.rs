@@ -76,3 +76,27 @@ fn skips() {
     assert_eq!(s.next(), None);
     assert_eq!(s.current(), None);
 }
+
+#[test]
+fn spans() {
+    use crate::span::{Position, Span};
+    let mut s = Scanner::new("añb\n  cd".chars());
+    s.next();
+    s.next();
+    assert_eq!(s.position(), Position { offset: 3, line: 1, column: 3 });
+    let (token, span) = s.extract_string_with_span();
+    assert_eq!(token, "añ");
+    assert_eq!(span, Span {
+        start: Position { offset: 0, line: 1, column: 1 },
+        end: Position { offset: 3, line: 1, column: 3 },
+    });
+    s.until_any(&['c']);
+    s.extract();
+    s.next();
+    s.next();
+    assert_eq!(s.extract_with_span().1, Span {
+        start: Position { offset: 7, line: 2, column: 3 },
+        end: Position { offset: 9, line: 2, column: 5 },
+    });
+    assert_eq!(s.position(), Position { offset: 9, line: 2, column: 5 });
+}
@@ -0,0 +1,58 @@
+#![deny(warnings)]
+
+// Locations of scanned items in their source
+
+/// A point in the source. `offset` is in bytes for chars (in items for
+/// other scanners), `line` and `column` are 1-based.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position { offset: 0, line: 1, column: 1 }
+    }
+}
+
+/// Where a token starts and where the next one would start
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// How scanning an item moves a Position. By default an item takes
+/// a column, chars count their UTF-8 bytes and start lines on '\n'.
+pub trait Advance {
+    fn advance(&self, pos: &mut Position) {
+        pos.offset += 1;
+        pos.column += 1;
+    }
+}
+
+impl Advance for char {
+    fn advance(&self, pos: &mut Position) {
+        pos.offset += self.len_utf8();
+        if *self == '\n' {
+            pos.line += 1;
+            pos.column = 1;
+        } else {
+            pos.column += 1;
+        }
+    }
+}
+
+impl Advance for String {}
+
+impl Position {
+    /// Position after scanning items starting from this one
+    pub fn after<'a, T: Advance + 'a>(mut self, items: impl IntoIterator<Item = &'a T>) -> Self {
+        for item in items {
+            item.advance(&mut self);
+        }
+        self
+    }
+}
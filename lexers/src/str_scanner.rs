@@ -0,0 +1,292 @@
+#![deny(warnings)]
+
+// A Scanner over a &str that doesn't copy, tokens are slices of the source
+
+use crate::char_scanner::{self, CharCursor, NumberStyle, WHITE};
+use crate::span::{Advance, Position, Span};
+
+pub struct StrScanner<'a> {
+    src: &'a str,
+    // byte offset where the buffer starts
+    start: usize,
+    // byte offset after the current char, start if there is none,
+    // src.len() + 1 once the scanner ran past the end
+    pos: usize,
+    // where the buffer starts in lines and columns
+    start_pos: Position,
+}
+
+impl<'a> Iterator for StrScanner<'a> {
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        if self.pos > self.src.len() {
+            return None;
+        }
+        match self.src[self.pos..].chars().next() {
+            Some(c) => {
+                self.pos += c.len_utf8();
+                Some(c)
+            }
+            None => {
+                self.pos = self.src.len() + 1;
+                None
+            }
+        }
+    }
+}
+
+impl<'a> StrScanner<'a> {
+    pub fn new(source: &'a str) -> StrScanner<'a> {
+        StrScanner {
+            src: source,
+            start: 0,
+            pos: 0,
+            start_pos: Position::default(),
+        }
+    }
+
+    // Allows getting current buffer position to backtrack, it's a byte
+    // offset in the source so it stays valid after extracting
+    pub fn buffer_pos(&self) -> usize {
+        self.pos
+    }
+
+    // Reset buffer position, normally used for backtracking
+    // If position is out of the buffer or not at a char boundary
+    // set_buffer_pos returns false
+    pub fn set_buffer_pos(&mut self, pos: usize) -> bool {
+        let len = self.src.len();
+        if pos < self.start || pos > len + 1 || (pos <= len && !self.src.is_char_boundary(pos)) {
+            return false;
+        }
+        self.pos = pos;
+        true
+    }
+
+    // Returns the current char on which the scanner is positioned
+    pub fn current(&self) -> Option<char> {
+        if self.pos == self.start || self.pos > self.src.len() {
+            return None;
+        }
+        self.src[self.start..self.pos].chars().next_back()
+    }
+
+    // Steps the scanner back and returns the char at that position
+    pub fn prev(&mut self) -> Option<char> {
+        if self.pos > self.src.len() {
+            self.pos = self.src.len();
+        } else if let Some(c) = self.current() {
+            self.pos -= c.len_utf8();
+        }
+        self.current()
+    }
+
+    // Returns the char ahead without actually advancing the scanner
+    pub fn peek(&mut self) -> Option<char> {
+        let backtrack = self.pos;
+        let peeked = self.next();
+        self.pos = backtrack;
+        peeked
+    }
+
+    // Returns the previous char without actually backtracking the scanner
+    pub fn peek_prev(&mut self) -> Option<char> {
+        let backtrack = self.pos;
+        let peeked = self.prev();
+        self.pos = backtrack;
+        peeked
+    }
+
+    // Returns a view of the current underlying buffer
+    pub fn view(&self) -> &'a str {
+        &self.src[self.start..self.pos.min(self.src.len())]
+    }
+
+    // Consumes the buffer into a new token (which can be ignored)
+    pub fn extract(&mut self) -> &'a str {
+        self.extract_with_span().0
+    }
+
+    // Like extract also returning where the token is in the source
+    pub fn extract_with_span(&mut self) -> (&'a str, Span) {
+        let token = self.view();
+        let start = self.start_pos;
+        self.start_pos = StrScanner::position(self);
+        self.start += token.len();
+        self.pos = self.start;
+        (token, Span { start, end: self.start_pos })
+    }
+
+    // Position right after the current char, where the next one starts
+    pub fn position(&self) -> Position {
+        let mut pos = self.start_pos;
+        self.view().chars().for_each(|c| c.advance(&mut pos));
+        pos
+    }
+
+    // Advance the scanner only if the next char is the expected one
+    // self.current() will return the matched char if accept matched
+    pub fn accept(&mut self, what: &char) -> Option<char> {
+        self.accept_any(&[*what])
+    }
+
+    // Advance the scanner only if the next char is in the 'any' set,
+    // self.current() will return the matched char if accept matched any
+    pub fn accept_any(&mut self, any: &[char]) -> Option<char> {
+        self.accept_char(any)
+    }
+
+    // Skip over the 'over' set, result is if the scanner was advanced,
+    // self.current() will return the last matching char
+    pub fn skip_all(&mut self, over: &[char]) -> bool {
+        self.skip_chars(over)
+    }
+
+    // Find a char in the 'any' set or EOF, return if the scanner advanced,
+    // self.current() returns the last non-matching char
+    pub fn until_any(&mut self, any: &[char]) -> bool {
+        let mut advanced = false;
+        while let Some(next) = self.peek() {
+            if any.contains(&next) {
+                break;
+            }
+            self.next();
+            advanced = true;
+        }
+        advanced
+    }
+
+    pub fn scan_whitespace(&mut self) -> Option<&'a str> {
+        self.skip_all(WHITE);
+        Some(self.extract())
+    }
+
+    // scan numbers like -?[0-9]+(\.[0-9]+)?([eE][+-][0-9]+)?
+    pub fn scan_number(&mut self) -> Option<&'a str> {
+        self.scan_number_with(NumberStyle::default())
+    }
+
+    // scan_number also accepting what style allows
+    pub fn scan_number_with(&mut self, style: NumberStyle) -> Option<&'a str> {
+        char_scanner::number(self, style).then(|| self.extract())
+    }
+
+    pub fn scan_math_op(&mut self) -> Option<&'a str> {
+        char_scanner::math_op(self).then(|| self.extract())
+    }
+
+    // scan integers like 0x34 0b10101 0o657
+    pub fn scan_integer(&mut self) -> Option<&'a str> {
+        char_scanner::integer(self).then(|| self.extract())
+    }
+
+    // scan a quoted string like "this is \"an\" example"
+    pub fn scan_quoted_string(&mut self, q: char) -> Option<&'a str> {
+        char_scanner::quoted_string(self, q).then(|| self.extract())
+    }
+
+    // scan a quoted string returning its value with escapes decoded,
+    // a bad escape or a missing closing quote is an error
+    pub fn scan_decoded_string(&mut self, q: char) -> Option<Result<String, String>> {
+        let start = StrScanner::position(self);
+        let value = char_scanner::decoded_string(self, q)?;
+        self.extract();
+        Some(value.map_err(|e| e.at(start)))
+    }
+
+    // scan identifiers like café, Δt or _x1 (XID_Start XID_Continue*)
+    pub fn scan_identifier(&mut self) -> Option<&'a str> {
+        char_scanner::identifier(self).then(|| self.extract())
+    }
+}
+
+impl<'a> CharCursor for StrScanner<'a> {
+    type Pos = usize;
+    fn next_char(&mut self) -> Option<char> {
+        self.next()
+    }
+    fn pos(&self) -> usize {
+        self.pos
+    }
+    fn set_pos(&mut self, pos: usize) {
+        self.set_buffer_pos(pos);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::{Scanner, StrScanner};
+
+    // Scans input like a math tokenizer with both scanners
+    fn tokens(input: &str) -> (Vec<String>, Vec<&str>) {
+        let mut chars = Scanner::new(input.chars());
+        let mut slices = StrScanner::new(input);
+        let mut expected = Vec::new();
+        let mut found = Vec::new();
+        loop {
+            chars.scan_whitespace();
+            slices.scan_whitespace();
+            let token = chars.scan_math_op()
+                .or_else(|| chars.scan_integer())
+                .or_else(|| chars.scan_number())
+                .or_else(|| chars.scan_identifier())
+                .or_else(|| chars.scan_quoted_string('"'));
+            let slice = slices.scan_math_op()
+                .or_else(|| slices.scan_integer())
+                .or_else(|| slices.scan_number())
+                .or_else(|| slices.scan_identifier())
+                .or_else(|| slices.scan_quoted_string('"'));
+            match (token, slice) {
+                (Some(t), Some(s)) => {
+                    expected.push(t);
+                    found.push(s);
+                }
+                (None, None) => return (expected, found),
+                other => panic!("Scanners disagree: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_scanner() {
+        let inputs = [
+            "3+4*2/-(1-5)^2^3",
+            "f(0x1F, -2.5e-3i) <= \"a \\\" b\" ** x_1",
+            "  ñandú + 1.e3 ",
+            "",
+        ];
+        for input in inputs {
+            let (expected, found) = tokens(input);
+            assert_eq!(expected, found);
+        }
+    }
+
+    #[test]
+    fn slices_and_backtracking() {
+        let src = String::from("día 42");
+        let mut s = StrScanner::new(&src);
+        assert_eq!(s.accept(&'d'), Some('d'));
+        assert!(s.until_any(&[' ']));
+        assert_eq!(s.current(), Some('a'));
+        assert_eq!(s.prev(), Some('í'));
+        assert!(!s.set_buffer_pos(2));
+        assert_eq!(s.next(), Some('a'));
+        let (word, span) = s.extract_with_span();
+        assert_eq!(word, "día");
+        assert_eq!((span.end.offset, span.end.column), (4, 4));
+        s.skip_all(&[' ']);
+        s.extract();
+        let backtrack = s.buffer_pos();
+        assert_eq!(s.next(), Some('4'));
+        assert!(s.set_buffer_pos(backtrack));
+        assert_eq!(s.scan_number(), Some("42"));
+        assert_eq!(s.next(), None);
+        assert_eq!(s.current(), None);
+        assert_eq!(s.prev(), None);
+        // tokens outlive the scanner, they borrow from the source
+        let word = StrScanner::new(&src).scan_identifier();
+        assert_eq!(word, Some("día"));
+    }
+}
@@ -0,0 +1,223 @@
+#![deny(warnings)]
+
+// Declarative longest-match tokenizers. Rules are literals, runs of a
+// character class or regexes, each with a kind. At every point the rule
+// with the longest match wins, ties go to the higher priority and then to
+// the rule added first. Skipped rules match but emit nothing.
+
+use crate::regex::Regex;
+use crate::span::{Advance, Position, Span};
+
+enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+struct Rule {
+    kind: String,
+    pattern: Pattern,
+    priority: i32,
+    skip: bool,
+}
+
+impl Rule {
+    // Length of the match at the start of input, empty matches don't count
+    fn match_len(&self, input: &str) -> Option<usize> {
+        match &self.pattern {
+            Pattern::Literal(text) => input.starts_with(text.as_str()).then_some(text.len()),
+            Pattern::Regex(regex) => regex.match_len(input),
+        }
+        .filter(|&len| len > 0)
+    }
+}
+
+#[derive(Default)]
+pub struct TokenizerBuilder {
+    rules: Vec<Rule>,
+    error: Option<String>,
+}
+
+impl TokenizerBuilder {
+    pub fn new() -> Self {
+        TokenizerBuilder::default()
+    }
+
+    fn add<S: Into<String>>(mut self, kind: S, pattern: Result<Pattern, String>) -> Self {
+        let kind = kind.into();
+        match pattern {
+            Ok(pattern) => self.rules.push(Rule { kind, pattern, priority: 0, skip: false }),
+            Err(e) => {
+                self.error.get_or_insert(format!("Bad rule {}: {}", kind, e));
+            }
+        }
+        self
+    }
+
+    // Tokens of `kind` spelled exactly like `text`
+    pub fn literal<S, T>(self, kind: S, text: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let text = text.into();
+        let pattern = match text.is_empty() {
+            true => Err("empty literal".to_string()),
+            false => Ok(Pattern::Literal(text)),
+        };
+        self.add(kind, pattern)
+    }
+
+    // Tokens of `kind` made of a run of chars in a class like [a-z_]
+    pub fn class<S: Into<String>>(self, kind: S, class: &str) -> Self {
+        let pattern = match class.starts_with('[') && class.ends_with(']') {
+            true => Regex::new(&format!("{}+", class)).map(Pattern::Regex),
+            false => Err(format!("{} is not a [class]", class)),
+        };
+        self.add(kind, pattern)
+    }
+
+    // Tokens of `kind` matching a regex (see lexers::Regex)
+    pub fn regex<S: Into<String>>(self, kind: S, regex: &str) -> Self {
+        let pattern = Regex::new(regex).map(Pattern::Regex);
+        self.add(kind, pattern)
+    }
+
+    // Priority of the last rule added when matches tie, default is 0
+    pub fn priority(mut self, priority: i32) -> Self {
+        match self.rules.last_mut() {
+            Some(rule) => rule.priority = priority,
+            None => self.missing_rule("priority"),
+        }
+        self
+    }
+
+    // Drop tokens matched by the last rule added, like whitespace
+    pub fn skip(mut self) -> Self {
+        match self.rules.last_mut() {
+            Some(rule) => rule.skip = true,
+            None => self.missing_rule("skip"),
+        }
+        self
+    }
+
+    fn missing_rule(&mut self, modifier: &str) {
+        self.error.get_or_insert(format!("No rule for {}", modifier));
+    }
+
+    pub fn build(self) -> Result<Tokenizer, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None if self.rules.is_empty() => Err("No rules".to_string()),
+            None => Ok(Tokenizer { rules: self.rules }),
+        }
+    }
+}
+
+pub struct Tokenizer {
+    rules: Vec<Rule>,
+}
+
+impl Tokenizer {
+    // Longest match at the start of input with the rule that made it
+    fn longest(&self, input: &str) -> Option<(usize, &Rule)> {
+        let mut best: Option<(usize, &Rule)> = None;
+        for rule in &self.rules {
+            if let Some(len) = rule.match_len(input) {
+                // earlier rules win full ties
+                let better = best.is_none_or(|(best_len, best_rule)| {
+                    (len, rule.priority) > (best_len, best_rule.priority)
+                });
+                if better {
+                    best = Some((len, rule));
+                }
+            }
+        }
+        best
+    }
+
+    // Iterate over the (kind, lexeme, span) tokens of input
+    pub fn tokens<'t, 'a>(&'t self, input: &'a str) -> Tokens<'t, 'a> {
+        Tokens { tokenizer: self, rest: input, position: Position::default(), failed: false }
+    }
+}
+
+pub struct Tokens<'t, 'a> {
+    tokenizer: &'t Tokenizer,
+    rest: &'a str,
+    position: Position,
+    failed: bool,
+}
+
+impl<'t, 'a> Iterator for Tokens<'t, 'a> {
+    type Item = Result<(&'t str, &'a str, Span), String>;
+    // Iteration stops after reporting input no rule matches
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() && !self.failed {
+            let (len, rule) = match self.tokenizer.longest(self.rest) {
+                Some(longest) => longest,
+                None => {
+                    self.failed = true;
+                    let found: String = self.rest.chars().take(10).collect();
+                    return Some(Err(format!(
+                        "No token matches at {}:{}: {:?}",
+                        self.position.line, self.position.column, found
+                    )));
+                }
+            };
+            let (lexeme, rest) = self.rest.split_at(len);
+            let start = self.position;
+            lexeme.chars().for_each(|c| c.advance(&mut self.position));
+            self.rest = rest;
+            if !rule.skip {
+                let span = Span { start, end: self.position };
+                return Some(Ok((rule.kind.as_str(), lexeme, span)));
+            }
+        }
+        None
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::TokenizerBuilder;
+
+    #[test]
+    fn longest_match() {
+        let tokenizer = TokenizerBuilder::new()
+            .class("ws", "[ \t\n]").skip()
+            .regex("comment", "#[^\n]*").skip()
+            .literal("if", "if").priority(1)
+            .class("id", "[a-z_]")
+            .regex("num", r"\d+(\.\d+)?")
+            .literal("=", "=")
+            .literal("==", "==")
+            .build()
+            .unwrap();
+        let tokens: Result<Vec<_>, _> = tokenizer
+            .tokens("if iffy == 1.5 # done\n  x=2")
+            .map(|t| t.map(|(kind, lexeme, _)| (kind, lexeme)))
+            .collect();
+        assert_eq!(tokens.unwrap(), vec![
+            ("if", "if"), ("id", "iffy"), ("==", "=="), ("num", "1.5"),
+            ("id", "x"), ("=", "="), ("num", "2"),
+        ]);
+        let spans: Vec<_> = tokenizer.tokens("a\n b é")
+            .map(|t| t.map(|(_, _, span)| (span.start.line, span.start.column, span.end.offset)))
+            .collect();
+        assert_eq!(spans, vec![Ok((1, 1, 1)), Ok((2, 2, 4)),
+                               Err("No token matches at 2:4: \"é\"".to_string())]);
+    }
+
+    #[test]
+    fn builder_errors() {
+        let error = |builder: TokenizerBuilder| builder.build().err();
+        assert_eq!(error(TokenizerBuilder::new()), Some("No rules".to_string()));
+        assert_eq!(error(TokenizerBuilder::new().skip()), Some("No rule for skip".to_string()));
+        assert_eq!(error(TokenizerBuilder::new().class("id", "a-z")),
+                   Some("Bad rule id: a-z is not a [class]".to_string()));
+        assert!(error(TokenizerBuilder::new().regex("num", "(1")).is_some());
+        assert!(error(TokenizerBuilder::new().literal("nothing", "")).is_some());
+    }
+}
@@ -0,0 +1,269 @@
+#![deny(warnings)]
+
+// Whitespace and comments between tokens, called trivia. Tokenizers can
+// skip it, or keep it attached to their tokens so that concatenating the
+// tokens with their trivia gives back the input.
+
+use crate::char_scanner::WHITE;
+use crate::scanner::Scanner;
+use crate::span::Span;
+
+/// Comment syntax. Line comments run until the end of the line, block
+/// comments can nest.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comments {
+    line: Vec<String>,
+    block: Vec<(String, String)>,
+}
+
+impl Comments {
+    pub fn new() -> Self {
+        Comments::default()
+    }
+
+    /// Comments from `start` until the end of the line, like # or //.
+    /// Panics if `start` is empty, it would match everywhere.
+    pub fn line<S: Into<String>>(mut self, start: S) -> Self {
+        let start = start.into();
+        assert!(!start.is_empty(), "Empty line comment start");
+        self.line.push(start);
+        self
+    }
+
+    /// Comments between `open` and `close`, like /* and */. Panics if
+    /// either is empty.
+    pub fn block<S, T>(mut self, open: S, close: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let (open, close) = (open.into(), close.into());
+        assert!(!open.is_empty() && !close.is_empty(), "Empty block comment delimiter");
+        self.block.push((open, close));
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+// Advance over text if the input continues with it
+fn accept_str<I: Iterator<Item = char>>(s: &mut Scanner<I>, text: &str) -> bool {
+    let backtrack = s.buffer_pos();
+    if text.chars().all(|c| s.accept(&c).is_some()) {
+        return true;
+    }
+    s.set_buffer_pos(backtrack);
+    false
+}
+
+impl<I: Iterator<Item = char>> Scanner<I> {
+    // Whitespace and comments ahead. An unterminated block comment is an
+    // error, the scanner is left at its start.
+    pub fn scan_trivia(&mut self, comments: &Comments) -> Result<Vec<Trivia>, String> {
+        self.trivia(comments, false)
+    }
+
+    // Trivia up to the end of the line (included) if line_end, else all
+    fn trivia(&mut self, comments: &Comments, line_end: bool) -> Result<Vec<Trivia>, String> {
+        let mut trivia = Vec::new();
+        loop {
+            let mut eol = false;
+            let kind = if line_end {
+                let spaces = self.skip_all(&[' ', '\t', '\r']);
+                eol = self.accept(&'\n').is_some();
+                (spaces || eol).then_some(TriviaKind::Whitespace)
+            } else {
+                self.skip_all(WHITE).then_some(TriviaKind::Whitespace)
+            };
+            let kind = match kind {
+                Some(kind) => kind,
+                None => match self.comment(comments)? {
+                    Some(kind) => kind,
+                    None => return Ok(trivia),
+                },
+            };
+            let (text, span) = self.extract_string_with_span();
+            trivia.push(Trivia { kind, text, span });
+            if eol {
+                return Ok(trivia);
+            }
+        }
+    }
+
+    pub(crate) fn comment(&mut self, comments: &Comments) -> Result<Option<TriviaKind>, String> {
+        if comments.line.iter().any(|start| accept_str(self, start)) {
+            self.until_any(&['\n']);
+            return Ok(Some(TriviaKind::LineComment));
+        }
+        for (open, close) in &comments.block {
+            let backtrack = self.buffer_pos();
+            let start = Scanner::position(self);
+            if !accept_str(self, open) {
+                continue;
+            }
+            let mut depth = 1;
+            while depth > 0 {
+                if accept_str(self, close) {
+                    depth -= 1;
+                } else if accept_str(self, open) {
+                    depth += 1;
+                } else if self.next().is_none() {
+                    self.set_buffer_pos(backtrack);
+                    return Err(format!("Unterminated comment at {}:{}", start.line, start.column));
+                }
+            }
+            return Ok(Some(TriviaKind::BlockComment));
+        }
+        Ok(None)
+    }
+}
+
+/// A token with the trivia before it, and after it up to the end of its line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TriviaToken {
+    pub leading: Vec<Trivia>,
+    pub text: String,
+    pub span: Span,
+    pub trailing: Vec<Trivia>,
+}
+
+impl TriviaToken {
+    /// The token with its trivia as it was in the input
+    pub fn full_text(&self) -> String {
+        let leading = self.leading.iter().map(|t| t.text.as_str());
+        let trailing = self.trailing.iter().map(|t| t.text.as_str());
+        leading.chain(Some(self.text.as_str())).chain(trailing).collect()
+    }
+}
+
+/// Tokens scanned by a function, like `|s| s.scan_identifier()`, keeping
+/// all the trivia around them. Trivia at the end of the input comes in a
+/// last token with empty text. Iteration stops after an error.
+pub struct Lossless<I: Iterator<Item = char>, F> {
+    src: Scanner<I>,
+    comments: Comments,
+    scan: F,
+    done: bool,
+}
+
+impl<I, F> Lossless<I, F>
+where
+    I: Iterator<Item = char>,
+    F: FnMut(&mut Scanner<I>) -> Option<String>,
+{
+    pub fn new(source: I, comments: Comments, scan: F) -> Self {
+        Lossless { src: Scanner::new(source), comments, scan, done: false }
+    }
+
+    fn token(&mut self) -> Result<Option<TriviaToken>, String> {
+        let leading = self.src.scan_trivia(&self.comments)?;
+        let start = self.src.position();
+        let text = match (self.scan)(&mut self.src) {
+            Some(text) if !text.is_empty() => text,
+            _ if self.src.peek().is_none() => {
+                self.done = true;
+                let span = Span { start, end: start };
+                let end = TriviaToken { leading, text: String::new(), span, trailing: Vec::new() };
+                return Ok(Some(end).filter(|end| !end.leading.is_empty()));
+            }
+            _ => return Err(format!("No token at {}:{}", start.line, start.column)),
+        };
+        let span = Span { start, end: self.src.position() };
+        let trailing = self.src.trivia(&self.comments, true)?;
+        Ok(Some(TriviaToken { leading, text, span, trailing }))
+    }
+}
+
+impl<I, F> Iterator for Lossless<I, F>
+where
+    I: Iterator<Item = char>,
+    F: FnMut(&mut Scanner<I>) -> Option<String>,
+{
+    type Item = Result<TriviaToken, String>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.token();
+        self.done |= token.is_err();
+        token.transpose()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::{Comments, Lossless, TriviaKind};
+    use crate::Scanner;
+
+    fn comments() -> Comments {
+        Comments::new().line("#").line("//").block("/*", "*/")
+    }
+
+    #[test]
+    fn scan_trivia() {
+        let mut s = Scanner::new(" # one\n/* a /* b */ c */x".chars());
+        let trivia = s.scan_trivia(&comments()).unwrap();
+        let kinds: Vec<_> = trivia.iter().map(|t| (t.kind, t.text.as_str())).collect();
+        assert_eq!(kinds, vec![
+            (TriviaKind::Whitespace, " "),
+            (TriviaKind::LineComment, "# one"),
+            (TriviaKind::Whitespace, "\n"),
+            (TriviaKind::BlockComment, "/* a /* b */ c */"),
+        ]);
+        assert_eq!(s.scan_identifier(), Some("x".to_string()));
+        let mut s = Scanner::new("  /* a /* b */".chars());
+        assert_eq!(s.scan_trivia(&comments()),
+                   Err("Unterminated comment at 1:3".to_string()));
+        assert_eq!(s.next(), Some('/'));
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty line comment start")]
+    fn empty_line_comment() {
+        Comments::new().line("");
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty block comment delimiter")]
+    fn empty_block_comment() {
+        Comments::new().block("(*", "");
+    }
+
+    #[test]
+    fn lossless() {
+        let input = "  x = 1 // one\n\n/* two */ y=x ; last\n  # end\n";
+        let tokens: Vec<_> = Lossless::new(input.chars(), comments(), |s| {
+            s.scan_identifier().or_else(|| s.scan_number()).or_else(|| s.scan_math_op())
+                .or_else(|| s.accept(&';').map(|_| s.extract_string()))
+        })
+        .collect::<Result<_, _>>()
+        .unwrap();
+        let text: String = tokens.iter().map(|t| t.full_text()).collect();
+        assert_eq!(text, input);
+        assert_eq!(tokens[2].text, "1");
+        assert_eq!(tokens[2].trailing.len(), 3);
+        assert_eq!(tokens[3].leading[0].text, "\n");
+        assert_eq!((tokens[3].span.start.line, tokens[3].span.start.column), (3, 11));
+        let end = tokens.last().unwrap();
+        assert_eq!((end.text.as_str(), end.leading.len()), ("", 3));
+
+        let mut error = Lossless::new("x ?".chars(), comments(), |s| s.scan_identifier());
+        assert_eq!(error.next().map(|t| t.unwrap().full_text()), Some("x ".to_string()));
+        assert_eq!(error.next(), Some(Err("No token at 1:3".to_string())));
+        assert_eq!(error.next(), None);
+    }
+}
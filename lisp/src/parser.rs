@@ -8,6 +8,7 @@ pub enum ParseError {
     UnexpectedCParen,
     UnexpectedEOF,
     NotImplemented,
+    BadToken(String),
 }
 
 #[derive(Clone, PartialEq, PartialOrd, Debug)]
@@ -28,7 +29,7 @@ impl string::ToString for LispExpr {
     fn to_string(&self) -> String {
         match self {
             &LispExpr::Symbol(ref s) => s.clone(),
-            &LispExpr::String(ref s) => s.clone(),
+            &LispExpr::String(ref s) => format!("{:?}", s),
             &LispExpr::Number(n) => format!("{}", n),
             &LispExpr::List(ref v) => {
                 let base = match v.first() {
@@ -63,7 +64,10 @@ impl Parser {
         where I: Iterator<Item=char>
     {
         match lex.next() {
-            None                        => Err(ParseError::UnexpectedEOF),
+            None => match lex.token_error() {
+                Some(error)             => Err(ParseError::BadToken(error.to_string())),
+                None                    => Err(ParseError::UnexpectedEOF),
+            },
             Some(LispToken::CParen)     => Err(ParseError::UnexpectedCParen),
             Some(LispToken::True)       => Ok(LispExpr::True),
             Some(LispToken::False)      => Ok(LispExpr::False),
@@ -1,4 +1,4 @@
-use crate::parser::{Parser, LispExpr};
+use crate::parser::{Parser, LispExpr, ParseError};
 
 #[test]
 fn test_lisp1() {
@@ -22,3 +22,15 @@ fn test_lisp1() {
     ]);
     assert_eq!(p.unwrap(), r);
 }
+
+#[test]
+fn test_strings() {
+    let p = Parser::parse_str(r#"(print "a\tb \"c\"")"#).unwrap();
+    let s = LispExpr::String(format!("a\tb \"c\""));
+    assert_eq!(p, LispExpr::List(vec![LispExpr::Symbol(format!("print")), s.clone()]));
+    assert_eq!(s.to_string(), r#""a\tb \"c\"""#);
+    assert_eq!(Parser::parse_str(r#""\q""#),
+               Err(ParseError::BadToken(format!("Bad escape \\q at 1:1"))));
+    assert_eq!(Parser::parse_str(r#"(a "\u{41")"#),
+               Err(ParseError::BadToken("Bad unicode escape at 1:4, expected \\u{HEX}".to_string())));
+}
@@ -1,4 +1,4 @@
-use lexers::Scanner;
+use lexers::{Advance, Scanner};
 
 #[derive(Clone,Debug,PartialEq)]
 pub enum TT {
@@ -20,8 +20,11 @@ pub struct Token {
     pub lexeme: String,
 }
 
+impl Advance for Token {}
+
 pub struct LoxScanner<I: Iterator<Item=char>> {
     src: Scanner<I>,
+    // line where the token being scanned starts
     line: usize,
     errors: bool,
 }
@@ -55,14 +58,11 @@ impl<I: Iterator<Item=char>> LoxScanner<I> {
 
     fn scan_restof_string(&mut self, q: char) -> bool {
         let backtrack = self.src.buffer_pos();
-        let orig_line = self.line;
         while let Some(n) = self.src.next() {
-            if n == '\n' { self.line += 1; }
             if n == '\\' { self.src.next(); continue; }
             if n == q { return true; }
         }
         self.src.set_buffer_pos(backtrack);
-        self.line = orig_line;
         false
     }
 
@@ -95,6 +95,7 @@ impl<I: Iterator<Item=char>> LoxScanner<I> {
     }
 
     fn scan_token(&mut self) -> Option<Token> {
+        self.line = self.src.position().line;
         let token = match self.src.next() {
             Some('(') => self.tokenize(TT::OPAREN),
             Some(')') => self.tokenize(TT::CPAREN),
@@ -135,7 +136,7 @@ impl<I: Iterator<Item=char>> LoxScanner<I> {
                 self.tokenize(TT::SLASH)
             },
             Some(' ') | Some('\t') | Some('\r') => None,
-            Some('\n') => { self.line += 1; None }, // track current line
+            Some('\n') => None,
             Some('"') => match self.scan_restof_string('"') {
                 true => self.tokenize(TT::Str(String::new())),
                 false => { self.error("unterminated string"); None }
@@ -63,3 +63,11 @@ fn test_eval8() {
     let expr = ShuntingParser::parse_str("-2^-3").unwrap();
     fuzzy_eq!(MathContext::new().eval(&expr).unwrap(), -0.125);
 }
+
+#[test]
+fn test_unicode_vars() {
+    let expr = ShuntingParser::parse_str("1_000 * Δt^2").unwrap();
+    let mut cx = MathContext::new();
+    cx.setvar("Δt", 0.5);
+    fuzzy_eq!(cx.eval(&expr).unwrap(), 250.0);
+}